@@ -1,5 +1,10 @@
 mod opts;
 mod process;
+mod utils;
 
-pub use opts::{Opts, SubCommand};
-pub use process::{process_csv, process_genpass};
+pub use opts::{CsvSubCommand, Opts, OutputFormat, SubCommand};
+pub use process::{
+    process_convert, process_csv, process_csv_batch, process_csv_concat, process_csv_join,
+    process_csv_show, process_decode, process_genpass,
+};
+pub use utils::{default_output, stdout_closed};
@@ -1,20 +1,55 @@
 use clap::Parser;
-use rcli::{process_csv, process_genpass, Opts, SubCommand};
+use rcli::{
+    default_output, process_convert, process_csv, process_csv_batch, process_csv_concat,
+    process_csv_join, process_csv_show, process_decode, process_genpass, stdout_closed,
+    CsvSubCommand, Opts, OutputFormat, SubCommand,
+};
 
 fn main() -> anyhow::Result<()> {
+    match run() {
+        // 下游命令不再读取输出时正常退出，与其他命令行工具在管道中的行为一致
+        Err(_) if stdout_closed() => Ok(()),
+        result => result,
+    }
+}
+
+fn run() -> anyhow::Result<()> {
     let opts = Opts::parse();
 
     match opts.cmd {
-        SubCommand::Csv(opts) => {
-            let output = if let Some(output) = opts.output {
-                output.clone()
-            } else {
-                // 要输出的文件格式
-                format!("output.{}", opts.format)
-            };
+        SubCommand::Csv(opts) => match &opts.cmd {
+            Some(CsvSubCommand::Show(opts)) => process_csv_show(opts)?,
+            Some(CsvSubCommand::Concat(opts)) => process_csv_concat(opts)?,
+            Some(CsvSubCommand::Join(opts)) => process_csv_join(opts)?,
+            None if opts.is_batch() => {
+                if opts.decode {
+                    return Err(anyhow::anyhow!("--decode takes a single input file"));
+                }
+                process_csv_batch(&opts)?;
+            }
+            None => {
+                let input = opts
+                    .input
+                    .first()
+                    .ok_or_else(|| anyhow::anyhow!("--input is required"))?;
+                if opts.decode {
+                    // 解码用于查看内容，默认输出到标准输出
+                    let output = opts.output.as_deref().unwrap_or("-");
+                    process_decode(input, output, opts.force, &opts.read)?;
+                    return Ok(());
+                }
+                let output = if let Some(output) = &opts.output {
+                    output.clone()
+                } else {
+                    let format = OutputFormat::resolve(opts.format, "-")?;
+                    default_output(input, format.extension())
+                };
 
-            process_csv(&opts.input, output, opts.format)?;
-        }
+                process_csv(input, &output, &opts)?;
+            }
+        },
+
+        SubCommand::Convert(opts) => process_convert(&opts)?,
 
         SubCommand::GenPass(opts) => {
             process_genpass(
@@ -23,6 +58,8 @@ fn main() -> anyhow::Result<()> {
                 opts.lowercase,
                 opts.number,
                 opts.symbol,
+                &opts.output,
+                opts.force,
             )?;
         }
     }
@@ -1,4 +1,5 @@
-use clap::Parser;
+use clap::{ArgAction, Parser};
+use encoding_rs::Encoding;
 use std::{fmt, path::Path, str::FromStr};
 
 #[derive(Debug, Parser)]
@@ -11,31 +12,329 @@ pub struct Opts {
 #[derive(Debug, Parser)]
 pub enum SubCommand {
     #[command(name = "csv", about = "Show CSV or convert to other formats")]
-    Csv(CsvOpts),
+    Csv(Box<CsvOpts>),
+
+    #[command(
+        name = "convert",
+        about = "Convert between JSON, YAML, TOML, CSV and other formats"
+    )]
+    Convert(Box<ConvertOpts>),
 
     #[command(name = "genpass", about = "Generate a random password")]
     GenPass(GenPassOpts),
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum OutputFormat {
     Json,
+    Ndjson,
+    Yaml,
+    Toml,
+    Csv,
+    Markdown,
+    Html,
+    Asciidoc,
+    Xml,
+    Msgpack,
+    Cbor,
+    Bson,
+    Parquet,
+    /// Arrow IPC 文件格式（Feather V2）
+    Arrow,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    Csv,
+    /// Excel（xlsx/xlsm/xlsb/xls）或 OpenDocument（ods）工作表
+    Excel,
+    Json,
+    Ndjson,
     Yaml,
     Toml,
+    Xml,
+    Msgpack,
+    Cbor,
+    Bson,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnError {
+    Fail,
+    Skip,
+    Collect,
+}
+
+/// Parquet/Arrow 输出的压缩算法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Snappy,
+    Gzip,
+    Zstd,
+    Lz4,
+}
+
+/// `csv join` 的连接方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinKind {
+    Inner,
+    Left,
+    Right,
+    Full,
+}
+
+/// `--sort-by` 中的一列
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortKey {
+    pub column: String,
+    pub mode: SortMode,
+    pub descending: bool,
+}
+
+/// 排序时比较值的方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortMode {
+    /// 按文本逐字符比较
+    Lexical,
+    /// 按数值比较，不是数字的值按文本比较并排在数字之后
+    Numeric,
+    /// 文本中的数字部分按数值比较，如 `v2` 排在 `v10` 之前
+    Natural,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    String,
+    Int,
+    Float,
+    Bool,
+    Date,
 }
 
 #[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct CsvOpts {
-    #[arg(short, long, value_parser= verify_input_file)]
+    #[command(subcommand)]
+    pub cmd: Option<CsvSubCommand>,
+    /// 输入文件，可以是多个文件或 glob 模式（如 `'exports/*.csv'`），多个文件时批量并行转换
+    // 使用子命令时不需要 input，由 clap 保证未使用子命令时必填
+    #[arg(short, long, value_parser = verify_input_pattern, num_args = 1.., required = true)]
+    pub input: Vec<String>,
+    /// 输出文件，默认与输入文件同名、扩展名为输出格式；标准输入时默认输出到标准输出
+    #[arg(short, long, conflicts_with = "out_dir")]
+    pub output: Option<String>,
+    /// 批量转换时输出文件所在的目录，不存在时自动创建；默认输出到各输入文件所在的目录
+    #[arg(long)]
+    pub out_dir: Option<String>,
+    /// 批量转换时同时转换的文件数，默认为 CPU 核数
+    #[arg(short, long)]
+    pub jobs: Option<usize>,
+    /// 覆盖已存在的输出文件
+    #[arg(long)]
+    pub force: bool,
+    /// 输出格式，默认按输出文件的扩展名判断，无法判断时为 JSON
+    #[arg(short, long, value_parser = parse_format)]
+    pub format: Option<OutputFormat>,
+    /// TOML 输出的顶层键名，默认使用输入文件名（不含扩展名）
+    #[arg(long)]
+    pub toml_root: Option<String>,
+    /// TOML 输出以该列（值必须唯一）作为键生成表，而不是表数组
+    #[arg(long)]
+    pub key_by: Option<String>,
+    /// 把 MessagePack/CBOR/BSON 输入解码为格式化的 JSON，默认输出到标准输出
+    #[arg(long)]
+    pub decode: bool,
+    /// XML 输出的根元素名
+    #[arg(long, default_value = "records")]
+    pub xml_root: String,
+    /// XML 输出把字段写为行元素的属性，而不是子元素
+    #[arg(long)]
+    pub xml_attributes: bool,
+    /// Parquet/Arrow 输出的压缩算法：none、snappy、gzip、zstd、lz4；
+    /// Parquet 默认 snappy，Arrow 默认不压缩且只支持 zstd、lz4
+    #[arg(long, value_parser = parse_compression)]
+    pub compression: Option<Compression>,
+    /// Parquet 每个 row group（Arrow 每个 record batch）的最大行数，默认 1048576
+    #[arg(long)]
+    pub row_group_size: Option<usize>,
+    #[command(flatten)]
+    pub read: CsvReadOpts,
+    #[command(flatten)]
+    pub sort: CsvSortOpts,
+}
+
+/// 在任意两种支持的格式之间转换整个文档
+#[derive(Debug, Parser)]
+pub struct ConvertOpts {
+    /// 输入文件，格式由 `--input-format`、扩展名或文件内容判断
+    #[arg(short, long, value_parser = verify_input_file)]
     pub input: String,
+    /// 输出文件，默认与输入文件同名、扩展名为输出格式；标准输入时默认输出到标准输出
     #[arg(short, long)]
     pub output: Option<String>,
-    #[arg(short, long, value_parser = parse_format , default_value = "json")]
-    pub format: OutputFormat,
-    #[arg(short, long, default_value_t = ',')]
-    pub delimiter: char,
-    #[arg(long, default_value_t = true)]
+    /// 覆盖已存在的输出文件
+    #[arg(long)]
+    pub force: bool,
+    /// 输出格式，默认按输出文件的扩展名判断，无法判断时为 JSON
+    #[arg(short, long, value_parser = parse_format)]
+    pub format: Option<OutputFormat>,
+    #[command(flatten)]
+    pub read: CsvReadOpts,
+}
+
+#[derive(Debug, Parser)]
+pub enum CsvSubCommand {
+    #[command(name = "show", about = "Show CSV as an aligned table")]
+    Show(CsvShowOpts),
+
+    #[command(
+        name = "concat",
+        about = "Concatenate files, aligning columns by header name"
+    )]
+    Concat(CsvConcatOpts),
+
+    #[command(name = "join", about = "Join two files on key columns")]
+    Join(CsvJoinOpts),
+}
+
+/// 读取输入相关的参数，在 csv 的各个子命令间共享
+#[derive(Debug, Clone, Parser)]
+pub struct CsvReadOpts {
+    /// 输入格式，默认按扩展名判断，标准输入默认为 CSV
+    #[arg(long, value_parser = parse_input_format)]
+    pub input_format: Option<InputFormat>,
+    #[arg(short, long, value_parser = parse_delimiter, default_value = ",")]
+    pub delimiter: u8,
+    /// XML 中表示一行记录的元素名；输入默认取根元素的每个子元素，输出默认为 `record`
+    #[arg(long)]
+    pub xml_row: Option<String>,
+    /// Excel/ODS 输入读取的工作表，名称或从 1 开始的序号，默认为第一个工作表
+    #[arg(long)]
+    pub sheet: Option<String>,
+    /// CSV 输入的编码，如 utf-8、gbk、gb18030、utf-16le；默认按 BOM 和内容自动判断
+    #[arg(long, value_parser = parse_encoding)]
+    pub encoding: Option<&'static Encoding>,
+    #[arg(long, default_value_t = true, action = ArgAction::Set)]
     pub header: bool,
+    /// 自定义列名，逗号分隔（无表头时默认使用 col1, col2, ...）；输出 CSV 时指定列顺序
+    #[arg(long, value_delimiter = ',')]
+    pub columns: Option<Vec<String>>,
+    /// 推断整列的类型，输出整数、浮点数、布尔值，空单元格输出 null；
+    /// XML、JSON 等输入按字段名推断记录顶层的字符串值
+    #[arg(long)]
+    pub infer: bool,
+    /// 指定列类型，覆盖推断结果，如 `name:string,dob:date`；XML、JSON 等输入只转换字符串值
+    #[arg(long, value_parser = parse_column_type, value_delimiter = ',')]
+    pub types: Vec<(String, ColumnType)>,
+    /// 保持 `address.city`、`tags[0]` 形式的列名，不还原为嵌套结构
+    #[arg(long)]
+    pub flat: bool,
+    /// 过滤记录，如 `Nationality == "Italy" && "Kit Number" < 20`
+    #[arg(long = "where")]
+    pub filter: Option<String>,
+    /// 只输出这些列，并按给定顺序排列
+    #[arg(long, value_delimiter = ',')]
+    pub select: Option<Vec<String>>,
+    /// 不输出这些列
+    #[arg(long, value_delimiter = ',')]
+    pub exclude: Vec<String>,
+    /// 重命名列，如 `"Kit Number=kit"`；新名字不能与其他列相同
+    #[arg(long, value_parser = parse_rename, value_delimiter = ',')]
+    pub rename: Vec<(String, String)>,
+    /// 按 schema 文件（YAML 或 JSON Schema）检查每一行，有违反时报告行号、列号并失败
+    #[arg(long, value_parser = verify_input_file)]
+    pub schema: Option<String>,
+    /// 遇到列数不符、非法 UTF-8 或类型转换失败的行时：fail 中止，skip 跳过，
+    /// collect 跳过并写入 `--rejects` 文件
+    #[arg(long, value_parser = parse_on_error, default_value = "fail")]
+    pub on_error: OnError,
+    /// `--on-error collect` 时记录被拒绝行的 CSV 文件，包含行号、错误和原始字段
+    #[arg(long)]
+    pub rejects: Option<String>,
+}
+
+/// 输出记录的排序与去重
+#[derive(Debug, Clone, Parser)]
+pub struct CsvSortOpts {
+    /// 按这些列排序，逗号分隔；每列可加 `:num` 或 `:natural`（默认按文本）以及 `:desc`，
+    /// 如 `"Kit Number:num:desc,Name"`；空值总是排在最后，值相同的行保持输入顺序
+    #[arg(long, value_parser = parse_sort_key, value_delimiter = ',')]
+    pub sort_by: Vec<SortKey>,
+    /// 这些列的值都相同的行只保留第一行（指定了 `--sort-by` 时为排序后的第一行）
+    #[arg(long, value_delimiter = ',')]
+    pub dedup_by: Vec<String>,
+    /// 排序使用的内存上限（MiB），超出时分段排序写入临时文件再归并
+    #[arg(long, default_value_t = 256)]
+    pub sort_memory: usize,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvShowOpts {
+    #[arg(short, long, value_parser= verify_input_file)]
+    pub input: String,
+    #[command(flatten)]
+    pub read: CsvReadOpts,
+    /// 只显示前 N 行
+    #[arg(long)]
+    pub head: Option<usize>,
+    /// 只显示最后 N 行
+    #[arg(long)]
+    pub tail: Option<usize>,
+    /// 单元格最大显示宽度，超出部分截断，0 表示不截断
+    #[arg(long, default_value_t = 40)]
+    pub max_width: usize,
+    /// 表格总宽度，超出时按列分页；默认使用终端宽度
+    #[arg(long)]
+    pub width: Option<usize>,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvConcatOpts {
+    /// 要合并的文件或 glob 模式，记录按文件的顺序输出
+    #[arg(required = true, value_parser = verify_input_pattern)]
+    pub input: Vec<String>,
+    /// 输出文件，默认为标准输出
+    #[arg(short, long)]
+    pub output: Option<String>,
+    /// 覆盖已存在的输出文件
+    #[arg(long)]
+    pub force: bool,
+    /// 输出格式，默认按输出文件的扩展名判断，无法判断时为 CSV
+    #[arg(short, long, value_parser = parse_format)]
+    pub format: Option<OutputFormat>,
+    /// 增加 `source_file` 列，记录每行来自哪个文件
+    #[arg(long)]
+    pub source_file: bool,
+    #[command(flatten)]
+    pub read: CsvReadOpts,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvJoinOpts {
+    /// 左表，逐行读取，输出按左表的行序
+    #[arg(long, value_parser = verify_input_file)]
+    pub left: String,
+    /// 右表，整个读入内存并按键分组
+    #[arg(long, value_parser = verify_input_file)]
+    pub right: String,
+    /// 连接的键列，逗号分隔；两边列名不同时写为 `left=right`，如 `Name=Player`
+    #[arg(long, required = true, value_parser = parse_join_key, value_delimiter = ',')]
+    pub on: Vec<(String, String)>,
+    /// 连接方式：inner、left、right、full
+    #[arg(long, value_parser = parse_join_kind, default_value = "inner")]
+    pub how: JoinKind,
+    /// 输出文件，默认为标准输出
+    #[arg(short, long)]
+    pub output: Option<String>,
+    /// 覆盖已存在的输出文件
+    #[arg(long)]
+    pub force: bool,
+    /// 输出格式，默认按输出文件的扩展名判断，无法判断时为 CSV
+    #[arg(short, long, value_parser = parse_format)]
+    pub format: Option<OutputFormat>,
+    #[command(flatten)]
+    pub read: CsvReadOpts,
 }
 
 #[derive(Debug, Parser)]
@@ -50,45 +349,426 @@ pub struct GenPassOpts {
     pub number: bool,
     #[arg(long, default_value_t = true)]
     pub symbol: bool,
+    /// 密码输出位置，默认为标准输出；写入的文件只有所有者可读写
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    /// 覆盖已存在的输出文件
+    #[arg(long)]
+    pub force: bool,
 }
 
 fn parse_format(format: &str) -> Result<OutputFormat, anyhow::Error> {
     format.parse()
 }
 
+fn parse_input_format(format: &str) -> Result<InputFormat, anyhow::Error> {
+    format.parse()
+}
+
+fn parse_encoding(label: &str) -> Result<&'static Encoding, anyhow::Error> {
+    Encoding::for_label(label.as_bytes())
+        .ok_or_else(|| anyhow::anyhow!("Unknown encoding `{}`", label))
+}
+
+fn parse_compression(s: &str) -> Result<Compression, anyhow::Error> {
+    s.parse()
+}
+
+fn parse_join_kind(s: &str) -> Result<JoinKind, anyhow::Error> {
+    s.parse()
+}
+
+fn parse_join_key(s: &str) -> Result<(String, String), anyhow::Error> {
+    match s.split_once('=') {
+        Some((left, right)) => Ok((left.to_string(), right.to_string())),
+        None => Ok((s.to_string(), s.to_string())),
+    }
+}
+
+fn parse_sort_key(s: &str) -> Result<SortKey, anyhow::Error> {
+    s.parse()
+}
+
+fn parse_on_error(s: &str) -> Result<OnError, anyhow::Error> {
+    s.parse()
+}
+
+fn parse_column_type(s: &str) -> Result<(String, ColumnType), anyhow::Error> {
+    let (name, ty) = s
+        .rsplit_once(':')
+        .ok_or_else(|| anyhow::anyhow!("Invalid column type `{}`, expected name:type", s))?;
+    Ok((name.to_string(), ty.parse()?))
+}
+
+fn parse_rename(s: &str) -> Result<(String, String), anyhow::Error> {
+    let (from, to) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow::anyhow!("Invalid rename `{}`, expected old=new", s))?;
+    Ok((from.to_string(), to.to_string()))
+}
+
+impl CsvOpts {
+    /// 有多个输入、使用了 glob 模式或指定了 `--out-dir` 时批量转换
+    pub fn is_batch(&self) -> bool {
+        self.out_dir.is_some()
+            || self.input.len() > 1
+            || self.input.iter().any(|input| is_glob_pattern(input))
+    }
+}
+
 impl From<OutputFormat> for &'static str {
     fn from(format: OutputFormat) -> Self {
         match format {
             OutputFormat::Json => "json",
+            OutputFormat::Ndjson => "ndjson",
             OutputFormat::Yaml => "yaml",
             OutputFormat::Toml => "toml",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Markdown => "markdown",
+            OutputFormat::Html => "html",
+            OutputFormat::Asciidoc => "asciidoc",
+            OutputFormat::Xml => "xml",
+            OutputFormat::Msgpack => "msgpack",
+            OutputFormat::Cbor => "cbor",
+            OutputFormat::Bson => "bson",
+            OutputFormat::Parquet => "parquet",
+            OutputFormat::Arrow => "arrow",
         }
     }
 }
 
+impl OutputFormat {
+    /// 确定输出格式：`--format` 未指定时按输出文件的扩展名判断，都无法判断时为 JSON；
+    /// 指定的格式与扩展名不符时报错，避免把 JSON 写进 `.yaml` 文件
+    pub fn resolve(format: Option<Self>, output: &str) -> anyhow::Result<Self> {
+        Self::resolve_or(format, output, OutputFormat::Json)
+    }
+
+    /// 同 `resolve`，都无法判断时为 `default`
+    pub fn resolve_or(format: Option<Self>, output: &str, default: Self) -> anyhow::Result<Self> {
+        match (format, Self::from_path(output)) {
+            (Some(format), Some(implied)) if format != implied => Err(anyhow::anyhow!(
+                "--format {} conflicts with output file `{}`, which implies {}",
+                format,
+                output,
+                implied
+            )),
+            (Some(format), _) | (None, Some(format)) => Ok(format),
+            (None, None) => Ok(default),
+        }
+    }
+
+    /// 按扩展名判断输出格式，没有扩展名或扩展名未知时为 `None`
+    pub fn from_path(path: &str) -> Option<Self> {
+        let ext = Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase());
+
+        match ext.as_deref()? {
+            "json" => Some(OutputFormat::Json),
+            "ndjson" | "jsonl" => Some(OutputFormat::Ndjson),
+            "yaml" | "yml" => Some(OutputFormat::Yaml),
+            "toml" => Some(OutputFormat::Toml),
+            "csv" => Some(OutputFormat::Csv),
+            "md" | "markdown" => Some(OutputFormat::Markdown),
+            "html" | "htm" => Some(OutputFormat::Html),
+            "adoc" | "asciidoc" => Some(OutputFormat::Asciidoc),
+            "xml" => Some(OutputFormat::Xml),
+            "msgpack" | "mpk" => Some(OutputFormat::Msgpack),
+            "cbor" => Some(OutputFormat::Cbor),
+            "bson" => Some(OutputFormat::Bson),
+            "parquet" => Some(OutputFormat::Parquet),
+            "arrow" | "feather" | "ipc" => Some(OutputFormat::Arrow),
+            _ => None,
+        }
+    }
+
+    /// 默认输出文件使用的扩展名
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Markdown => "md",
+            OutputFormat::Asciidoc => "adoc",
+            format => format.into(),
+        }
+    }
+
+    /// 是否为整体写出一个文档的格式；其余格式逐条写出记录
+    pub fn is_document(self) -> bool {
+        matches!(
+            self,
+            OutputFormat::Json
+                | OutputFormat::Yaml
+                | OutputFormat::Toml
+                | OutputFormat::Msgpack
+                | OutputFormat::Cbor
+        )
+    }
+
+    /// 是否为需要确定列类型的列式格式
+    pub fn is_columnar(self) -> bool {
+        matches!(self, OutputFormat::Parquet | OutputFormat::Arrow)
+    }
+}
+
 impl FromStr for OutputFormat {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "json" => Ok(OutputFormat::Json),
+            "ndjson" => Ok(OutputFormat::Ndjson),
             "yaml" => Ok(OutputFormat::Yaml),
             "toml" => Ok(OutputFormat::Toml),
+            "csv" => Ok(OutputFormat::Csv),
+            "markdown" | "md" => Ok(OutputFormat::Markdown),
+            "html" => Ok(OutputFormat::Html),
+            "asciidoc" | "adoc" => Ok(OutputFormat::Asciidoc),
+            "xml" => Ok(OutputFormat::Xml),
+            "msgpack" => Ok(OutputFormat::Msgpack),
+            "cbor" => Ok(OutputFormat::Cbor),
+            "bson" => Ok(OutputFormat::Bson),
+            "parquet" => Ok(OutputFormat::Parquet),
+            "arrow" | "ipc" | "feather" => Ok(OutputFormat::Arrow),
             _ => Err(anyhow::anyhow!("Invalid format")),
         }
     }
 }
 
+impl InputFormat {
+    /// 按扩展名判断输入格式，未知扩展名按 CSV 处理
+    pub fn from_path(path: &str) -> Self {
+        Self::from_extension(path).unwrap_or(InputFormat::Csv)
+    }
+
+    /// 按扩展名判断输入格式，没有扩展名或扩展名未知时为 `None`
+    pub fn from_extension(path: &str) -> Option<Self> {
+        let ext = Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase());
+
+        match ext.as_deref()? {
+            "csv" | "tsv" | "txt" => Some(InputFormat::Csv),
+            "json" => Some(InputFormat::Json),
+            "ndjson" | "jsonl" => Some(InputFormat::Ndjson),
+            "yaml" | "yml" => Some(InputFormat::Yaml),
+            "toml" => Some(InputFormat::Toml),
+            "xml" => Some(InputFormat::Xml),
+            "msgpack" | "mpk" => Some(InputFormat::Msgpack),
+            "cbor" => Some(InputFormat::Cbor),
+            "bson" => Some(InputFormat::Bson),
+            "xlsx" | "xlsm" | "xlsb" | "xls" | "ods" => Some(InputFormat::Excel),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for InputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(InputFormat::Csv),
+            "json" => Ok(InputFormat::Json),
+            "ndjson" | "jsonl" => Ok(InputFormat::Ndjson),
+            "yaml" | "yml" => Ok(InputFormat::Yaml),
+            "toml" => Ok(InputFormat::Toml),
+            "xml" => Ok(InputFormat::Xml),
+            "msgpack" => Ok(InputFormat::Msgpack),
+            "cbor" => Ok(InputFormat::Cbor),
+            "bson" => Ok(InputFormat::Bson),
+            "excel" | "xlsx" | "xls" | "ods" => Ok(InputFormat::Excel),
+            _ => Err(anyhow::anyhow!("Invalid input format")),
+        }
+    }
+}
+
+impl FromStr for OnError {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fail" => Ok(OnError::Fail),
+            "skip" => Ok(OnError::Skip),
+            "collect" => Ok(OnError::Collect),
+            _ => Err(anyhow::anyhow!(
+                "Invalid --on-error mode, expected fail, skip or collect"
+            )),
+        }
+    }
+}
+
+impl FromStr for JoinKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "inner" => Ok(JoinKind::Inner),
+            "left" => Ok(JoinKind::Left),
+            "right" => Ok(JoinKind::Right),
+            "full" | "outer" => Ok(JoinKind::Full),
+            _ => Err(anyhow::anyhow!(
+                "Invalid join kind, expected inner, left, right or full"
+            )),
+        }
+    }
+}
+
+impl FromStr for SortKey {
+    type Err = anyhow::Error;
+
+    /// `column[:num|:lexical|:natural][:asc|:desc]`，列名本身可以包含 `:`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut column = s;
+        let mut mode = None;
+        let mut descending = None;
+        while let Some((rest, suffix)) = column.rsplit_once(':') {
+            match suffix {
+                "num" | "numeric" if mode.is_none() => mode = Some(SortMode::Numeric),
+                "lex" | "lexical" if mode.is_none() => mode = Some(SortMode::Lexical),
+                "natural" if mode.is_none() => mode = Some(SortMode::Natural),
+                "asc" if descending.is_none() && mode.is_none() => descending = Some(false),
+                "desc" if descending.is_none() && mode.is_none() => descending = Some(true),
+                _ => break,
+            }
+            column = rest;
+        }
+        if column.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Invalid sort key `{}`, expected column[:num|:lexical|:natural][:asc|:desc]",
+                s
+            ));
+        }
+        Ok(SortKey {
+            column: column.to_string(),
+            mode: mode.unwrap_or(SortMode::Lexical),
+            descending: descending.unwrap_or(false),
+        })
+    }
+}
+
+impl FromStr for Compression {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" | "uncompressed" => Ok(Compression::None),
+            "snappy" => Ok(Compression::Snappy),
+            "gzip" => Ok(Compression::Gzip),
+            "zstd" => Ok(Compression::Zstd),
+            "lz4" => Ok(Compression::Lz4),
+            _ => Err(anyhow::anyhow!(
+                "Invalid compression, expected none, snappy, gzip, zstd or lz4"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Compression::None => "none",
+            Compression::Snappy => "snappy",
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+            Compression::Lz4 => "lz4",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for ColumnType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "string" => Ok(ColumnType::String),
+            "int" => Ok(ColumnType::Int),
+            "float" => Ok(ColumnType::Float),
+            "bool" => Ok(ColumnType::Bool),
+            "date" => Ok(ColumnType::Date),
+            _ => Err(anyhow::anyhow!("Invalid column type `{}`", s)),
+        }
+    }
+}
+
 impl fmt::Display for OutputFormat {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", Into::<&str>::into(*self))
     }
 }
 
+fn parse_delimiter(delimiter: &str) -> Result<u8, anyhow::Error> {
+    let c = match delimiter {
+        "\\t" | "tab" => '\t',
+        _ => {
+            let mut chars = delimiter.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => c,
+                _ => return Err(anyhow::anyhow!("Delimiter must be a single character")),
+            }
+        }
+    };
+
+    if c.is_ascii() {
+        Ok(c as u8)
+    } else {
+        Err(anyhow::anyhow!("Delimiter must be an ASCII character"))
+    }
+}
+
+/// glob 模式在转换时展开，本身存在的文件名（如含有 `[`）按普通文件处理
+pub fn is_glob_pattern(input: &str) -> bool {
+    input.contains(['*', '?', '[']) && !Path::new(input).exists()
+}
+
+fn verify_input_pattern(input: &str) -> Result<String, &'static str> {
+    if is_glob_pattern(input) {
+        Ok(input.into())
+    } else {
+        verify_input_file(input)
+    }
+}
+
 fn verify_input_file(filename: &str) -> Result<String, &'static str> {
-    if Path::new(filename).exists() {
+    // `-` 表示从标准输入读取
+    if filename == "-" || Path::new(filename).exists() {
         Ok(filename.into())
     } else {
         Err("File does not exist")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(column: &str, mode: SortMode, descending: bool) -> SortKey {
+        SortKey {
+            column: column.to_string(),
+            mode,
+            descending,
+        }
+    }
+
+    #[test]
+    fn parse_sort_keys() {
+        let cases = [
+            ("Name", key("Name", SortMode::Lexical, false)),
+            (
+                "Kit Number:num:desc",
+                key("Kit Number", SortMode::Numeric, true),
+            ),
+            ("id:natural", key("id", SortMode::Natural, false)),
+            ("a:b:lex:asc", key("a:b", SortMode::Lexical, false)),
+            ("time:desc:num", key("time:desc", SortMode::Numeric, false)),
+            ("x:", key("x:", SortMode::Lexical, false)),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(input.parse::<SortKey>().unwrap(), expected, "{}", input);
+        }
+        assert!(":num".parse::<SortKey>().is_err());
+        assert!("".parse::<SortKey>().is_err());
+    }
+}
@@ -0,0 +1,122 @@
+use anyhow::{anyhow, Result};
+use rayon::prelude::*;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::csv_convert::process_csv;
+use crate::opts::{is_glob_pattern, CsvOpts, OutputFormat};
+use crate::utils::default_output;
+
+/// 批量转换多个文件，各文件并行转换，互不影响
+///
+/// 结束后在标准错误中逐个报告转换结果，有文件转换失败时返回错误
+pub fn process_csv_batch(opts: &CsvOpts) -> Result<()> {
+    if opts.output.is_some() {
+        return Err(anyhow!(
+            "--output cannot be used when converting multiple files, use --out-dir"
+        ));
+    }
+    if opts.read.rejects.is_some() {
+        return Err(anyhow!(
+            "--rejects cannot be used when converting multiple files"
+        ));
+    }
+    let format = OutputFormat::resolve(opts.format, "-")?;
+    let inputs = expand_inputs(&opts.input)?;
+    let tasks = output_paths(&inputs, opts.out_dir.as_deref(), format)?;
+    if let Some(dir) = &opts.out_dir {
+        fs::create_dir_all(dir)?;
+    }
+
+    // 未指定 `--jobs` 时为 0，即使用 CPU 核数
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(opts.jobs.unwrap_or(0))
+        .build()?;
+    let results: Vec<Result<()>> = pool.install(|| {
+        tasks
+            .par_iter()
+            .map(|(input, output)| process_csv(input, output, opts))
+            .collect()
+    });
+
+    let mut failed = 0;
+    for ((input, output), result) in tasks.iter().zip(&results) {
+        match result {
+            Ok(()) => eprintln!("ok      {} -> {}", input, output),
+            Err(e) => {
+                eprintln!("failed  {}: {:#}", input, e);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(anyhow!("{} of {} file(s) failed", failed, tasks.len()));
+    }
+    eprintln!("Converted {} file(s)", tasks.len());
+    Ok(())
+}
+
+/// 展开 glob 模式并去掉重复的文件，保持参数中的顺序，同一模式匹配的文件按路径排序
+pub(crate) fn expand_inputs(patterns: &[String]) -> Result<Vec<String>> {
+    let mut inputs: Vec<String> = Vec::new();
+    for pattern in patterns {
+        if pattern == "-" {
+            return Err(anyhow!(
+                "Standard input cannot be used together with multiple input files"
+            ));
+        }
+        let matched = if is_glob_pattern(pattern) {
+            let mut paths = glob::glob(pattern)?
+                .filter_map(|entry| entry.ok())
+                .filter(|path| path.is_file())
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect::<Vec<_>>();
+            if paths.is_empty() {
+                return Err(anyhow!("No files match `{}`", pattern));
+            }
+            paths.sort();
+            paths
+        } else {
+            vec![pattern.clone()]
+        };
+        for path in matched {
+            if !inputs.contains(&path) {
+                inputs.push(path);
+            }
+        }
+    }
+    Ok(inputs)
+}
+
+/// 每个输入的输出文件：`--out-dir` 下或输入所在目录下的同名文件；两个输入输出到同一文件时报错
+fn output_paths(
+    inputs: &[String],
+    out_dir: Option<&str>,
+    format: OutputFormat,
+) -> Result<Vec<(String, String)>> {
+    let mut seen: HashMap<PathBuf, &str> = HashMap::new();
+    let mut tasks = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        let output = match out_dir {
+            Some(dir) => {
+                let name = Path::new(input).with_extension(format.extension());
+                let name = name.file_name().unwrap_or_default();
+                Path::new(dir).join(name).to_string_lossy().into_owned()
+            }
+            None => default_output(input, format.extension()),
+        };
+        if let Some(other) = seen.insert(PathBuf::from(&output), input) {
+            return Err(anyhow!(
+                "`{}` and `{}` would both be written to `{}`",
+                other,
+                input,
+                output
+            ));
+        }
+        tasks.push((input.clone(), output));
+    }
+    Ok(tasks)
+}
@@ -0,0 +1,155 @@
+use anyhow::{anyhow, Result};
+use bson::{Bson, Document};
+use serde_json::Value;
+use std::{
+    fs::File,
+    io::{self, BufRead, BufWriter, Seek, SeekFrom, Write},
+};
+
+use super::reader::input_format;
+use super::writer::RecordWriter;
+use crate::opts::{CsvReadOpts, InputFormat};
+use crate::utils::{create_output, get_reader};
+
+/// 输出为一个 MessagePack 数组
+///
+/// 数组长度要写在开头，因此先把记录写到临时文件，结束时写出长度后再复制过去
+pub(crate) struct MsgpackWriter<W> {
+    out: W,
+    spool: BufWriter<File>,
+    count: u32,
+}
+
+impl<W: Write> MsgpackWriter<W> {
+    pub fn new(out: W) -> Result<Self> {
+        Ok(Self {
+            out,
+            spool: BufWriter::new(tempfile::tempfile()?),
+            count: 0,
+        })
+    }
+}
+
+impl<W: Write> RecordWriter for MsgpackWriter<W> {
+    fn write_record(&mut self, record: &Value) -> Result<()> {
+        rmp_serde::encode::write(&mut self.spool, record)?;
+        self.count = self
+            .count
+            .checked_add(1)
+            .ok_or_else(|| anyhow!("Too many records for a MessagePack array"))?;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        // fixarray、array 16、array 32 三种长度格式
+        match self.count {
+            n @ 0..=15 => self.out.write_all(&[0x90 | n as u8])?,
+            n @ 16..=0xffff => {
+                self.out.write_all(&[0xdc])?;
+                self.out.write_all(&(n as u16).to_be_bytes())?;
+            }
+            n => {
+                self.out.write_all(&[0xdd])?;
+                self.out.write_all(&n.to_be_bytes())?;
+            }
+        }
+        let mut spool = self.spool.into_inner().map_err(|e| e.into_error())?;
+        spool.seek(SeekFrom::Start(0))?;
+        io::copy(&mut spool, &mut self.out)?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// 输出为一个不定长的 CBOR 数组，可以直接逐条写出
+pub(crate) struct CborWriter<W> {
+    out: W,
+    started: bool,
+}
+
+impl<W: Write> CborWriter<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            started: false,
+        }
+    }
+
+    fn start(&mut self) -> Result<()> {
+        if !self.started {
+            // 不定长数组的开始标记，以 0xff 结束
+            self.out.write_all(&[0x9f])?;
+            self.started = true;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> RecordWriter for CborWriter<W> {
+    fn write_record(&mut self, record: &Value) -> Result<()> {
+        self.start()?;
+        ciborium::into_writer(record, &mut self.out)?;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        self.start()?;
+        self.out.write_all(&[0xff])?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// 每条记录输出为一个 BSON 文档，依次拼接（与 mongodump 的 .bson 文件相同）
+pub(crate) struct BsonWriter<W> {
+    out: W,
+}
+
+impl<W: Write> BsonWriter<W> {
+    pub fn new(out: W) -> Self {
+        Self { out }
+    }
+}
+
+impl<W: Write> RecordWriter for BsonWriter<W> {
+    fn write_record(&mut self, record: &Value) -> Result<()> {
+        bson::to_document(record)?.to_writer(&mut self.out)?;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// 读取整个 MessagePack/CBOR 文档；BSON 文件中依次拼接的文档读为数组
+pub(crate) fn read_document(input: &str, format: InputFormat) -> Result<Value> {
+    let mut reader = get_reader(input)?;
+    let doc = match format {
+        InputFormat::Msgpack => rmp_serde::from_read(reader)?,
+        InputFormat::Cbor => ciborium::from_reader(reader)?,
+        InputFormat::Bson => {
+            let mut docs = Vec::new();
+            while !reader.fill_buf()?.is_empty() {
+                let doc = Document::from_reader(&mut reader)
+                    .map_err(|e| anyhow!("BSON document {}: {}", docs.len() + 1, e))?;
+                docs.push(Bson::Document(doc).into_relaxed_extjson());
+            }
+            Value::Array(docs)
+        }
+        _ => return Err(anyhow!("Expected a MessagePack, CBOR or BSON file")),
+    };
+    Ok(doc)
+}
+
+/// 把 MessagePack/CBOR/BSON 文件解码为格式化的 JSON，便于查看
+pub fn process_decode(input: &str, output: &str, force: bool, opts: &CsvReadOpts) -> Result<()> {
+    let doc = read_document(input, input_format(input, opts))?;
+    let (mut writer, file) = create_output(output, force)?;
+    serde_json::to_writer_pretty(&mut writer, &doc)?;
+    writeln!(writer)?;
+    writer.flush()?;
+    drop(writer);
+    file.commit()
+}
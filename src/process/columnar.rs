@@ -0,0 +1,129 @@
+use anyhow::{anyhow, Result};
+use arrow_ipc::{writer::FileWriter, writer::IpcWriteOptions, CompressionType};
+use arrow_json::reader::{infer_json_schema, ReaderBuilder};
+use parquet::{
+    arrow::ArrowWriter,
+    basic::{Compression as ParquetCompression, GzipLevel, ZstdLevel},
+    file::properties::WriterProperties,
+};
+use serde_json::Value;
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Seek, SeekFrom, Write},
+    sync::Arc,
+};
+
+use super::writer::{RecordWriter, WriterConfig};
+use crate::opts::Compression;
+
+/// 列式输出格式
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Columnar {
+    Parquet,
+    /// Arrow IPC 文件格式（Feather V2）
+    Arrow,
+}
+
+/// 输出为 Parquet 或 Arrow IPC 文件
+///
+/// 列式格式要先确定 schema，因此记录先以 NDJSON 写入临时文件，结束时按记录中的值类型
+/// （与 `--infer` 的 JSON 输出一致）生成 schema，再按 `--row-group-size` 分批编码
+pub(crate) struct ColumnarWriter<W> {
+    out: W,
+    format: Columnar,
+    spool: BufWriter<File>,
+    compression: Option<Compression>,
+    row_group_size: usize,
+}
+
+impl<W: Write> ColumnarWriter<W> {
+    pub fn new(out: W, format: Columnar, config: &WriterConfig) -> Result<Self> {
+        Ok(Self {
+            out,
+            format,
+            spool: BufWriter::new(tempfile::tempfile()?),
+            compression: config.compression,
+            row_group_size: config.row_group_size,
+        })
+    }
+}
+
+impl<W: Write> RecordWriter for ColumnarWriter<W> {
+    fn write_record(&mut self, record: &Value) -> Result<()> {
+        serde_json::to_writer(&mut self.spool, record)?;
+        self.spool.write_all(b"\n")?;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        let mut spool = self.spool.into_inner().map_err(|e| e.into_error())?;
+        spool.seek(SeekFrom::Start(0))?;
+        let (schema, _) = infer_json_schema(BufReader::new(&spool), None)?;
+        let schema = Arc::new(schema);
+        spool.seek(SeekFrom::Start(0))?;
+        let batches = ReaderBuilder::new(schema.clone())
+            .with_batch_size(self.row_group_size)
+            // 同一列中混有数字和字符串时，推断为字符串列，数字按原样转为字符串
+            .with_coerce_primitive(true)
+            .build(BufReader::new(spool))?;
+
+        // Parquet 写入器要求输出可以跨线程发送，先写到临时文件再复制到输出
+        let mut encoded = tempfile::tempfile()?;
+        match self.format {
+            Columnar::Parquet => {
+                let props = WriterProperties::builder()
+                    .set_compression(parquet_compression(self.compression))
+                    .set_max_row_group_size(self.row_group_size)
+                    .build();
+                let mut writer =
+                    ArrowWriter::try_new(BufWriter::new(&mut encoded), schema, Some(props))?;
+                for batch in batches {
+                    writer.write(&batch?)?;
+                }
+                writer.close()?;
+            }
+            Columnar::Arrow => {
+                let options = IpcWriteOptions::default()
+                    .try_with_compression(ipc_compression(self.compression)?)?;
+                let mut writer = FileWriter::try_new_with_options(
+                    BufWriter::new(&mut encoded),
+                    &schema,
+                    options,
+                )?;
+                for batch in batches {
+                    writer.write(&batch?)?;
+                }
+                writer.finish()?;
+            }
+        }
+
+        encoded.seek(SeekFrom::Start(0))?;
+        io::copy(&mut encoded, &mut self.out)?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// Parquet 默认使用 snappy 压缩，与 pyarrow、Spark 等工具一致
+fn parquet_compression(compression: Option<Compression>) -> ParquetCompression {
+    match compression.unwrap_or(Compression::Snappy) {
+        Compression::None => ParquetCompression::UNCOMPRESSED,
+        Compression::Snappy => ParquetCompression::SNAPPY,
+        Compression::Gzip => ParquetCompression::GZIP(GzipLevel::default()),
+        Compression::Zstd => ParquetCompression::ZSTD(ZstdLevel::default()),
+        Compression::Lz4 => ParquetCompression::LZ4_RAW,
+    }
+}
+
+/// Arrow IPC 默认不压缩，只支持 zstd 和 lz4
+fn ipc_compression(compression: Option<Compression>) -> Result<Option<CompressionType>> {
+    match compression {
+        None | Some(Compression::None) => Ok(None),
+        Some(Compression::Zstd) => Ok(Some(CompressionType::ZSTD)),
+        Some(Compression::Lz4) => Ok(Some(CompressionType::LZ4_FRAME)),
+        Some(compression) => Err(anyhow!(
+            "Arrow output does not support {} compression, use zstd or lz4",
+            compression
+        )),
+    }
+}
@@ -0,0 +1,299 @@
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use serde_json::Value;
+use std::io::{Read, Write};
+
+use super::csv_convert::{convert_records, writer_config};
+use super::reader::{expect_object, into_records, load_document};
+use super::writer::new_writer;
+use crate::opts::{ConvertOpts, CsvReadOpts, InputFormat, OutputFormat};
+use crate::utils::{create_output, default_output, get_reader, spool_stdin};
+
+/// 按内容判断格式时读取的文件开头的字节数
+const SNIFF_LEN: usize = 64 * 1024;
+
+/// 在任意两种支持的格式之间转换
+///
+/// JSON/YAML/TOML/MessagePack/CBOR 整体输出一个文档，如把 YAML 配置转为 TOML；其余格式逐条输出记录。
+/// CSV、工作表、XML 和 NDJSON 输入总是按记录读取，使用 `--where`、`--select` 等记录相关的参数时
+/// 其他输入也按记录读取
+pub fn process_convert(opts: &ConvertOpts) -> Result<()> {
+    let format = OutputFormat::resolve(opts.format, opts.output.as_deref().unwrap_or("-"))?;
+    let output = match &opts.output {
+        Some(output) => output.clone(),
+        None => default_output(&opts.input, format.extension()),
+    };
+    // 标准输入要先读一部分判断格式，再交给各格式的读取器，因此先转存到临时文件
+    let spooled = match (opts.input.as_str(), opts.read.input_format) {
+        ("-", None) => Some(spool_stdin()?),
+        _ => None,
+    };
+    let input = match &spooled {
+        Some(file) => file.path().to_string_lossy().into_owned(),
+        None => opts.input.clone(),
+    };
+
+    let input_format = match opts
+        .read
+        .input_format
+        .or_else(|| InputFormat::from_extension(&input))
+    {
+        Some(format) => format,
+        None => sniff_format(&input)?,
+    };
+    let read = CsvReadOpts {
+        input_format: Some(input_format),
+        ..opts.read.clone()
+    };
+    let by_record = matches!(
+        input_format,
+        InputFormat::Csv | InputFormat::Excel | InputFormat::Xml | InputFormat::Ndjson
+    ) || read.filter.is_some()
+        || read.select.is_some()
+        || !read.exclude.is_empty()
+        || !read.rename.is_empty();
+
+    // 逐条读取的输入也逐条写出，JSON/YAML/MessagePack/CBOR 的记录写出器输出的就是记录数组；
+    // TOML 的顶层不能是数组，不必读完再报错
+    if by_record {
+        if format == OutputFormat::Toml {
+            return check_toml(&Value::Array(Vec::new()));
+        }
+        let config = writer_config(&input, &read);
+        return convert_records(&input, &output, opts.force, format, &read, None, &config);
+    }
+    if !format.is_document() {
+        let config = writer_config(&input, &read);
+        let records = document_records(load_document(&input, input_format)?, format)?;
+        let (out, file) = create_output(&output, opts.force)?;
+        let mut writer = new_writer(format, out, &config)?;
+        for (i, record) in records.into_iter().enumerate() {
+            writer.write_record(&expect_object(record, i)?)?;
+        }
+        writer.finish()?;
+        return file.commit();
+    }
+
+    let doc = load_document(&input, input_format)?;
+    let (mut writer, file) = create_output(&output, opts.force)?;
+    write_document(&doc, format, &mut writer)?;
+    writer.flush()?;
+    drop(writer);
+    file.commit()
+}
+
+/// 逐条输出的格式需要记录：数组中的每个元素为一条记录，只含一个数组的表使用该数组，
+/// 其他对象整体作为一条记录
+fn document_records(doc: Value, format: OutputFormat) -> Result<Vec<Value>> {
+    match doc {
+        Value::Array(items) => Ok(items),
+        Value::Object(map) if map.len() == 1 && map.values().all(Value::is_array) => {
+            into_records(Value::Object(map))
+        }
+        Value::Object(map) => Ok(vec![Value::Object(map)]),
+        other => Err(anyhow!(
+            "{} output needs records, but the input is a single {}",
+            format,
+            type_name(&other)
+        )),
+    }
+}
+
+fn write_document(doc: &Value, format: OutputFormat, out: &mut dyn Write) -> Result<()> {
+    match format {
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, doc)?;
+            writeln!(out)?;
+        }
+        OutputFormat::Yaml => serde_yaml::to_writer(out, doc)?,
+        OutputFormat::Toml => {
+            check_toml(doc)?;
+            out.write_all(toml::to_string_pretty(doc)?.as_bytes())?;
+        }
+        OutputFormat::Msgpack => rmp_serde::encode::write(out, doc)?,
+        OutputFormat::Cbor => ciborium::into_writer(doc, out)?,
+        _ => unreachable!("{} output is written record by record", format),
+    }
+    Ok(())
+}
+
+/// TOML 的顶层必须是表，没有 null，整数为 64 位有符号整数；
+/// 写出前检查并指出具体位置，而不是报笼统的序列化错误
+fn check_toml(doc: &Value) -> Result<()> {
+    match doc {
+        Value::Object(map) => map
+            .iter()
+            .try_for_each(|(key, value)| check_toml_value(value, key)),
+        Value::Array(_) => Err(anyhow!(
+            "TOML cannot represent a top-level array, the document must be a table; \
+             use `rcli csv -f toml --toml-root <name>` to write records under a key"
+        )),
+        other => Err(anyhow!(
+            "TOML cannot represent a top-level {}, the document must be a table",
+            type_name(other)
+        )),
+    }
+}
+
+fn check_toml_value(value: &Value, path: &str) -> Result<()> {
+    match value {
+        Value::Null => Err(anyhow!("TOML has no null value, found null at `{}`", path)),
+        Value::Number(n) if n.is_u64() && n.as_i64().is_none() => Err(anyhow!(
+            "TOML integers are 64-bit signed, {} at `{}` is out of range",
+            n,
+            path
+        )),
+        Value::Object(map) => map
+            .iter()
+            .try_for_each(|(key, value)| check_toml_value(value, &format!("{}.{}", path, key))),
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .try_for_each(|(i, value)| check_toml_value(value, &format!("{}[{}]", path, i))),
+        _ => Ok(()),
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// 没有可识别的扩展名（如标准输入）时按内容判断格式
+fn sniff_format(path: &str) -> Result<InputFormat> {
+    let mut prefix = Vec::new();
+    get_reader(path)?
+        .take(SNIFF_LEN as u64)
+        .read_to_end(&mut prefix)?;
+    let text = match std::str::from_utf8(&prefix) {
+        Ok(text) => text,
+        // 末尾被截断的多字节字符
+        Err(e) if e.error_len().is_none() => {
+            std::str::from_utf8(&prefix[..e.valid_up_to()]).unwrap_or_default()
+        }
+        Err(_) => return sniff_binary(path, &prefix),
+    };
+    if text
+        .chars()
+        .any(|c| c.is_control() && !matches!(c, '\t' | '\r' | '\n'))
+    {
+        return sniff_binary(path, &prefix);
+    }
+
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+    let format = match text.chars().next() {
+        Some('<') => InputFormat::Xml,
+        // 第一行本身是完整的 JSON 且后面还有内容时为 NDJSON
+        Some('{') => match text.split_once('\n') {
+            Some((first, rest))
+                if serde_json::from_str::<Value>(first).is_ok() && !rest.trim().is_empty() =>
+            {
+                InputFormat::Ndjson
+            }
+            _ => InputFormat::Json,
+        },
+        // `[` 开头也可能是 TOML 的 `[table]` 或 `[[array]]`；被截断的 JSON 仍按 JSON 处理
+        Some('[') => match serde_json::from_str::<Value>(text) {
+            Err(e) if !e.is_eof() => InputFormat::Toml,
+            _ => InputFormat::Json,
+        },
+        _ => sniff_line(text),
+    };
+    Ok(format)
+}
+
+/// 按第一个非注释行判断：`key = value` 为 TOML，`key: value` 或 `- item` 为 YAML，否则为 CSV
+fn sniff_line(text: &str) -> InputFormat {
+    let line = text
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .unwrap_or_default();
+    let toml = Regex::new(r#"^[A-Za-z0-9_.\-"' ]+=\s*\S"#).unwrap();
+    let yaml = Regex::new(r"^(---|- |-$|[^,]+:(\s|$))").unwrap();
+    if toml.is_match(line) {
+        InputFormat::Toml
+    } else if yaml.is_match(line) {
+        InputFormat::Yaml
+    } else {
+        InputFormat::Csv
+    }
+}
+
+/// 二进制输入：BSON 以小端的文档长度开头并以 0 结尾，否则依次尝试按 CBOR 和 MessagePack 解码
+fn sniff_binary(path: &str, prefix: &[u8]) -> Result<InputFormat> {
+    if let Some(len) = prefix.get(..4) {
+        let len = u32::from_le_bytes([len[0], len[1], len[2], len[3]]) as usize;
+        if len >= 5 && len <= prefix.len() && prefix[len - 1] == 0 {
+            return Ok(InputFormat::Bson);
+        }
+    }
+    if ciborium::from_reader::<Value, _>(get_reader(path)?).is_ok() {
+        return Ok(InputFormat::Cbor);
+    }
+    if rmp_serde::from_read::<_, Value>(get_reader(path)?).is_ok() {
+        return Ok(InputFormat::Msgpack);
+    }
+    Err(anyhow!(
+        "Cannot detect the input format, use --input-format to specify it"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn sniff(content: &[u8]) -> InputFormat {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(content).unwrap();
+        sniff_format(&file.path().to_string_lossy()).unwrap()
+    }
+
+    #[test]
+    fn sniffs_text_formats() {
+        assert_eq!(sniff(b"<rows><row/></rows>"), InputFormat::Xml);
+        assert_eq!(sniff(b"\xef\xbb\xbf  {\"a\": 1}"), InputFormat::Json);
+        assert_eq!(sniff(b"{\"a\": 1}\n{\"a\": 2}\n"), InputFormat::Ndjson);
+        assert_eq!(sniff(b"{\n  \"a\": 1\n}\n"), InputFormat::Json);
+        assert_eq!(sniff(b"[1, 2]"), InputFormat::Json);
+        // 被截断的 JSON 数组仍按 JSON 处理
+        assert_eq!(sniff(b"[{\"a\": 1},"), InputFormat::Json);
+        assert_eq!(sniff(b"[server]\nhost = \"a\"\n"), InputFormat::Toml);
+        assert_eq!(sniff(b"# comment\ntitle = \"x\"\n"), InputFormat::Toml);
+        assert_eq!(sniff(b"name: x\nlist:\n  - 1\n"), InputFormat::Yaml);
+        assert_eq!(sniff(b"---\n- a\n"), InputFormat::Yaml);
+        assert_eq!(sniff(b"a,b\n1,2\n"), InputFormat::Csv);
+        // 含逗号的行不是 YAML 的 `key: value`
+        assert_eq!(sniff(b"time,note: x\n1,2\n"), InputFormat::Csv);
+    }
+
+    #[test]
+    fn sniffs_binary_formats() {
+        let bson = bson::to_vec(&bson::doc! { "a": 1 }).unwrap();
+        assert_eq!(sniff(&bson), InputFormat::Bson);
+        let mut cbor = Vec::new();
+        ciborium::into_writer(&json!([{"a": 1}]), &mut cbor).unwrap();
+        assert_eq!(sniff(&cbor), InputFormat::Cbor);
+        let msgpack = rmp_serde::to_vec(&json!({"a": [1, 2]})).unwrap();
+        assert_eq!(sniff(&msgpack), InputFormat::Msgpack);
+    }
+
+    #[test]
+    fn checks_toml_documents() {
+        assert!(check_toml(&json!({"a": {"b": [1, "x"]}})).is_ok());
+        let err = |doc: Value| check_toml(&doc).unwrap_err().to_string();
+        assert!(err(json!([{"a": 1}])).contains("top-level array"));
+        assert!(err(json!("x")).contains("top-level string"));
+        assert!(err(json!({"a": {"b": null}})).contains("`a.b`"));
+        assert!(err(json!({"a": [1, u64::MAX]})).contains("out of range"));
+    }
+}
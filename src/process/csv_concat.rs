@@ -0,0 +1,101 @@
+use anyhow::{anyhow, Result};
+use serde_json::{Map, Value};
+use std::{
+    collections::HashSet,
+    io::{BufRead, BufReader, BufWriter, Seek, SeekFrom, Write},
+};
+
+use super::batch::expand_inputs;
+use super::csv_convert::writer_config;
+use super::nested::unflatten;
+use super::reader::{missing_value, read_opts_for, read_records, should_nest};
+use super::writer::new_writer;
+use crate::opts::{CsvConcatOpts, CsvReadOpts, OutputFormat};
+use crate::utils::create_output;
+
+/// `--source-file` 增加的列名
+const SOURCE_COLUMN: &str = "source_file";
+
+/// 按列名合并多个文件的记录
+///
+/// 输出的列为所有文件列的并集，按首次出现的顺序排列，文件中没有的列填空值。
+/// 列名要读完所有文件才能确定，因此记录先以 NDJSON 写入临时文件，内存占用与文件大小无关
+pub fn process_csv_concat(opts: &CsvConcatOpts) -> Result<()> {
+    let output = opts.output.as_deref().unwrap_or("-");
+    let format = OutputFormat::resolve_or(opts.format, output, OutputFormat::Csv)?;
+    let inputs = expand_inputs(&opts.input)?;
+    if opts.read.rejects.is_some() && inputs.len() > 1 {
+        return Err(anyhow!(
+            "--rejects cannot be used when concatenating multiple files"
+        ));
+    }
+
+    // 按 CSV 中的原始列名对齐，读取时不还原嵌套结构，对齐后再还原
+    let read = CsvReadOpts {
+        flat: true,
+        ..opts.read.clone()
+    };
+    let nest: Vec<bool> = inputs
+        .iter()
+        .map(|input| should_nest(input, &opts.read))
+        .collect();
+
+    let mut columns: Vec<String> = Vec::new();
+    let mut seen = HashSet::new();
+    let mut spool = BufWriter::new(tempfile::tempfile()?);
+    for (index, input) in inputs.iter().enumerate() {
+        read_records(
+            input,
+            &read_opts_for(input, &read, format),
+            opts.force,
+            |record| {
+                if let Value::Object(map) = &record {
+                    for key in map.keys() {
+                        if seen.insert(key.clone()) {
+                            columns.push(key.clone());
+                        }
+                    }
+                }
+                serde_json::to_writer(&mut spool, &(index, record))?;
+                spool.write_all(b"\n")?;
+                Ok(())
+            },
+        )
+        .map_err(|e| anyhow!("{}: {:#}", input, e))?;
+    }
+    if opts.source_file && seen.contains(SOURCE_COLUMN) {
+        return Err(anyhow!(
+            "Column `{}` already exists, cannot add --source-file",
+            SOURCE_COLUMN
+        ));
+    }
+
+    let missing = missing_value(opts.read.infer || format.is_columnar());
+    let mut spool = spool.into_inner().map_err(|e| e.into_error())?;
+    spool.seek(SeekFrom::Start(0))?;
+
+    let (out, file) = create_output(output, opts.force)?;
+    let mut writer = new_writer(format, out, &writer_config(&inputs[0], &opts.read))?;
+    for line in BufReader::new(spool).lines() {
+        let (index, mut record): (usize, Map<String, Value>) = serde_json::from_str(&line?)?;
+        let mut aligned = Map::with_capacity(columns.len() + 1);
+        for column in &columns {
+            let value = record.remove(column).unwrap_or_else(|| missing.clone());
+            aligned.insert(column.clone(), value);
+        }
+        if opts.source_file {
+            aligned.insert(
+                SOURCE_COLUMN.to_string(),
+                Value::String(inputs[index].clone()),
+            );
+        }
+        let aligned = Value::Object(aligned);
+        writer.write_record(&if nest[index] {
+            unflatten(aligned)?
+        } else {
+            aligned
+        })?;
+    }
+    writer.finish()?;
+    file.commit()
+}
@@ -1,10 +1,12 @@
-use anyhow::Result;
-use csv::Reader;
+use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
-use std::fs;
+use std::path::Path;
 
-use crate::opts::OutputFormat;
+use super::reader::{input_format, read_opts_for, read_records};
+use super::sort::read_sorted;
+use super::writer::{new_writer, WriterConfig};
+use crate::opts::{CsvOpts, CsvReadOpts, CsvSortOpts, InputFormat, OutputFormat};
+use crate::utils::create_output;
 
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "PascalCase")]
@@ -19,31 +21,97 @@ struct Player {
     kit: u8,
 }
 
-pub fn process_csv(input: &str, output: String, format: OutputFormat) -> Result<()> {
-    let mut reader = Reader::from_path(input)?;
-    let mut ret = Vec::with_capacity(128);
-    let header = reader.headers()?.clone();
-
-    for result in reader.records() {
-        let record = result?;
-        let json_value = header.iter().zip(record.iter()).collect::<Value>();
-        ret.push(json_value);
+/// 流式转换：逐条读取、逐条写出，内存占用与文件大小无关
+///
+/// 输入为 JSON/YAML/TOML 时（按扩展名判断）读取其中的记录数组，可配合 `-f csv` 转回 CSV。
+/// 输入、输出为 `-` 时分别使用标准输入、标准输出
+pub fn process_csv(input: &str, output: &str, opts: &CsvOpts) -> Result<()> {
+    let format = OutputFormat::resolve(opts.format, output)?;
+    if opts.key_by.is_some() && !matches!(format, OutputFormat::Toml) {
+        return Err(anyhow!("--key-by is only supported for TOML output"));
+    }
+    if (opts.compression.is_some() || opts.row_group_size.is_some()) && !format.is_columnar() {
+        return Err(anyhow!(
+            "--compression and --row-group-size are only supported for Parquet and Arrow output"
+        ));
+    }
+    if opts.row_group_size == Some(0) {
+        return Err(anyhow!("--row-group-size must be greater than 0"));
     }
 
-    let content = match format {
-        OutputFormat::Json => serde_json::to_string_pretty(&ret)?,
-        OutputFormat::Yaml => serde_yaml::to_string(&ret)?,
-        OutputFormat::Toml => {
-            // TOML 需要一个结构化的数据格式，我们将使用一个包含数组的结构
-            #[derive(Serialize)]
-            struct TomlData {
-                players: Vec<Value>,
-            }
-            let toml_data = TomlData { players: ret };
-            toml::to_string_pretty(&toml_data)?
-        }
+    let defaults = writer_config(input, &opts.read);
+    let config = WriterConfig {
+        toml_root: opts
+            .toml_root
+            .clone()
+            .unwrap_or_else(|| default_toml_root(input)),
+        key_by: opts.key_by.clone(),
+        xml_root: opts.xml_root.clone(),
+        xml_attributes: opts.xml_attributes,
+        compression: opts.compression,
+        row_group_size: opts.row_group_size.unwrap_or(defaults.row_group_size),
+        ..defaults
+    };
+    convert_records(
+        input,
+        output,
+        opts.force,
+        format,
+        &opts.read,
+        Some(&opts.sort),
+        &config,
+    )
+}
+
+/// 读取输入中的记录，以 `format` 逐条写出；全部写完后输出文件才出现在 `output`
+///
+/// 指定了 `sort` 时按其中的列排序、去重后再写出
+pub(crate) fn convert_records(
+    input: &str,
+    output: &str,
+    force: bool,
+    format: OutputFormat,
+    read: &CsvReadOpts,
+    sort: Option<&CsvSortOpts>,
+    config: &WriterConfig,
+) -> Result<()> {
+    let (out, file) = create_output(output, force)?;
+    let mut writer = new_writer(format, out, config)?;
+
+    let read = read_opts_for(input, read, format);
+    let write = |record| writer.write_record(&record);
+    let header = match sort {
+        Some(sort) => read_sorted(input, &read, sort, force, write)?,
+        None => read_records(input, &read, force, write)?,
     };
+    if let Some(header) = header {
+        writer.set_header(header);
+    }
+    writer.finish()?;
+    file.commit()
+}
+
+/// 由读取参数决定的写入配置，其余使用默认值
+pub(crate) fn writer_config(input: &str, read: &CsvReadOpts) -> WriterConfig {
+    WriterConfig {
+        delimiter: read.delimiter,
+        header: read.header,
+        // CSV 和工作表输入的 `--columns` 已在读取时作为列名使用
+        columns: match input_format(input, read) {
+            InputFormat::Csv | InputFormat::Excel => None,
+            _ => read.columns.clone(),
+        },
+        xml_row: read.xml_row.clone().unwrap_or_else(|| "record".to_string()),
+        ..WriterConfig::default()
+    }
+}
 
-    fs::write(output, content)?;
-    Ok(())
+/// TOML 顶层键名默认取输入文件名，如 `juventus.csv` 为 `juventus`，标准输入为 `records`
+fn default_toml_root(input: &str) -> String {
+    Path::new(input)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .filter(|_| input != "-")
+        .unwrap_or("records")
+        .to_string()
 }
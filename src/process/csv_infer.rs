@@ -0,0 +1,425 @@
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use csv::StringRecord;
+use serde_json::{Map, Number, Value};
+use std::collections::HashMap;
+
+use super::nested::ColumnCheck;
+use crate::opts::ColumnType;
+
+/// `--types date` 支持的日期格式，统一输出为 ISO 8601（YYYY-MM-DD）
+const DATE_FORMATS: &[&str] = &[
+    "%Y-%m-%d",
+    "%Y/%m/%d",
+    "%d/%m/%Y",
+    "%d.%m.%Y",
+    "%b %d, %Y",
+    "%B %d, %Y",
+    "%d %b %Y",
+    "%d %B %Y",
+];
+
+/// 单列的推断状态，记录该列所有非空值还能匹配哪些类型
+#[derive(Debug, Clone)]
+struct Candidate {
+    int: bool,
+    float: bool,
+    bool: bool,
+}
+
+impl Default for Candidate {
+    fn default() -> Self {
+        Self {
+            int: true,
+            float: true,
+            bool: true,
+        }
+    }
+}
+
+impl Candidate {
+    fn observe(&mut self, value: &str) {
+        if value.is_empty() {
+            return;
+        }
+        // 与 `parse_typed` 一致，去掉首尾空白后再判断
+        let value = value.trim();
+        let leading_zero = has_leading_zero(value);
+        let int = value.parse::<i64>().is_ok();
+        self.int = self.int && !leading_zero && int;
+        // 超出 i64 范围的整数（如 20 位的 ID）转为浮点数会丢失精度，保留为字符串
+        self.float = self.float
+            && !leading_zero
+            && (int || !is_integer_literal(value))
+            && parse_float(value).is_some();
+        self.bool = self.bool && parse_bool_strict(value).is_some();
+    }
+
+    fn resolve(&self) -> ColumnType {
+        if self.int {
+            ColumnType::Int
+        } else if self.float {
+            ColumnType::Float
+        } else if self.bool {
+            ColumnType::Bool
+        } else {
+            ColumnType::String
+        }
+    }
+}
+
+/// 逐行观察数据，推断每一列的类型
+#[derive(Debug)]
+pub(crate) struct TypeInference {
+    candidates: Vec<Candidate>,
+}
+
+impl TypeInference {
+    pub fn new(width: usize) -> Self {
+        Self {
+            candidates: vec![Candidate::default(); width],
+        }
+    }
+
+    pub fn observe(&mut self, record: &StringRecord) {
+        for (candidate, value) in self.candidates.iter_mut().zip(record.iter()) {
+            candidate.observe(value);
+        }
+    }
+
+    pub fn finish(self) -> Vec<ColumnType> {
+        self.candidates.iter().map(Candidate::resolve).collect()
+    }
+}
+
+/// 按列类型把 CSV 记录转换为 JSON 对象
+#[derive(Debug)]
+pub(crate) struct RecordConverter {
+    header: StringRecord,
+    types: Vec<ColumnType>,
+    empty_as_null: bool,
+}
+
+impl RecordConverter {
+    /// `inferred` 为 `None` 时所有列均为字符串；`overrides` 中的列优先于推断结果
+    pub fn new(
+        header: StringRecord,
+        inferred: Option<Vec<ColumnType>>,
+        overrides: &[(String, ColumnType)],
+    ) -> Result<Self> {
+        let empty_as_null = inferred.is_some();
+        let mut types = inferred.unwrap_or_else(|| vec![ColumnType::String; header.len()]);
+
+        for (name, ty) in overrides {
+            let idx = header
+                .iter()
+                .position(|h| h == name)
+                .ok_or_else(|| anyhow!("Unknown column `{}` in --types", name))?;
+            types[idx] = *ty;
+        }
+
+        Ok(Self {
+            header,
+            types,
+            empty_as_null,
+        })
+    }
+
+    pub fn convert(&self, record: &StringRecord) -> Result<Value> {
+        let mut map = Map::with_capacity(self.header.len());
+        for ((name, ty), value) in self.header.iter().zip(&self.types).zip(record.iter()) {
+            let value = self
+                .convert_cell(*ty, value)
+                .map_err(|e| anyhow!("column `{}`: {}", name, e))?;
+            map.insert(name.to_string(), value);
+        }
+        Ok(Value::Object(map))
+    }
+
+    fn convert_cell(&self, ty: ColumnType, value: &str) -> Result<Value> {
+        if value.is_empty() && (self.empty_as_null || ty != ColumnType::String) {
+            return Ok(Value::Null);
+        }
+        parse_typed(ty, value)
+    }
+}
+
+/// XML、JSON 等非表格输入按字段名推断类型，只观察记录顶层字段中的字符串值
+#[derive(Debug, Default)]
+pub(crate) struct FieldInference {
+    candidates: HashMap<String, Candidate>,
+}
+
+impl FieldInference {
+    pub fn observe(&mut self, record: &Value) {
+        let Value::Object(map) = record else {
+            return;
+        };
+        for (name, value) in map {
+            let candidate = self.candidates.entry(name.clone()).or_default();
+            if let Value::String(s) = value {
+                candidate.observe(s);
+            }
+        }
+    }
+
+    pub fn finish(self) -> HashMap<String, ColumnType> {
+        self.candidates
+            .into_iter()
+            .map(|(name, candidate)| (name, candidate.resolve()))
+            .collect()
+    }
+}
+
+/// 按字段名的类型转换非表格输入的记录，与 [`RecordConverter`] 的规则相同，只转换字符串值
+#[derive(Debug)]
+pub(crate) struct FieldConverter {
+    types: HashMap<String, ColumnType>,
+    empty_as_null: bool,
+    /// 未推断类型时，`--types` 中的列名要等读到第一条记录才能检查
+    columns: ColumnCheck,
+    count: usize,
+}
+
+impl FieldConverter {
+    /// `inferred` 为 `None` 时只转换 `overrides` 中的列
+    pub fn new(
+        inferred: Option<HashMap<String, ColumnType>>,
+        overrides: &[(String, ColumnType)],
+    ) -> Result<Self> {
+        let empty_as_null = inferred.is_some();
+        let mut columns = ColumnCheck::default();
+        let mut types = match inferred {
+            Some(types) => {
+                if let Some((name, _)) =
+                    overrides.iter().find(|(name, _)| !types.contains_key(name))
+                {
+                    return Err(anyhow!("Unknown column `{}` in --types", name));
+                }
+                types
+            }
+            None => {
+                columns = columns.keys("--types", overrides.iter().map(|(name, _)| name.clone()));
+                HashMap::new()
+            }
+        };
+        types.extend(overrides.iter().cloned());
+        Ok(Self {
+            types,
+            empty_as_null,
+            columns,
+            count: 0,
+        })
+    }
+
+    pub fn convert(&mut self, record: Value) -> Result<Value> {
+        self.count += 1;
+        if record.is_object() {
+            self.columns.check(&record)?;
+        }
+        let Value::Object(mut map) = record else {
+            return Ok(record);
+        };
+
+        for (name, value) in map.iter_mut() {
+            let (Some(ty), Value::String(s)) = (self.types.get(name), &*value) else {
+                continue;
+            };
+            *value = if s.is_empty() && (self.empty_as_null || *ty != ColumnType::String) {
+                Value::Null
+            } else {
+                parse_typed(*ty, s)
+                    .map_err(|e| anyhow!("record {}: column `{}`: {}", self.count, name, e))?
+            };
+        }
+        Ok(Value::Object(map))
+    }
+}
+
+/// 按指定类型解析单元格
+pub(crate) fn parse_typed(ty: ColumnType, value: &str) -> Result<Value> {
+    match ty {
+        ColumnType::String => Ok(Value::String(value.to_string())),
+        ColumnType::Int => value
+            .trim()
+            .parse::<i64>()
+            .map(Value::from)
+            .map_err(|_| anyhow!("`{}` is not an integer", value)),
+        ColumnType::Float => parse_float(value)
+            .map(Value::Number)
+            .ok_or_else(|| anyhow!("`{}` is not a number", value)),
+        ColumnType::Bool => parse_bool(value)
+            .map(Value::Bool)
+            .ok_or_else(|| anyhow!("`{}` is not a boolean", value)),
+        ColumnType::Date => parse_date(value)
+            .map(|d| Value::String(d.format("%Y-%m-%d").to_string()))
+            .ok_or_else(|| anyhow!("`{}` is not a recognized date", value)),
+    }
+}
+
+/// 推断时不把带前导零的值（如邮编 `007`）当作数字，避免丢失信息
+fn has_leading_zero(value: &str) -> bool {
+    let digits = value.strip_prefix('-').unwrap_or(value).as_bytes();
+    digits.len() > 1 && digits[0] == b'0' && digits[1].is_ascii_digit()
+}
+
+/// 可选的正负号后全是数字
+fn is_integer_literal(value: &str) -> bool {
+    let digits = value.strip_prefix(['-', '+']).unwrap_or(value);
+    !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
+fn parse_float(value: &str) -> Option<Number> {
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|f| f.is_finite())
+        .and_then(Number::from_f64)
+}
+
+fn parse_bool_strict(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "true" | "yes" | "y" | "1" => Some(true),
+        "false" | "no" | "n" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    let value = value.trim();
+    DATE_FORMATS
+        .iter()
+        .find_map(|fmt| NaiveDate::parse_from_str(value, fmt).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn infer(rows: &[&[&str]]) -> Vec<ColumnType> {
+        let mut inference = TypeInference::new(rows[0].len());
+        for row in rows {
+            inference.observe(&StringRecord::from(row.to_vec()));
+        }
+        inference.finish()
+    }
+
+    #[test]
+    fn infers_column_types() {
+        use ColumnType::*;
+        assert_eq!(
+            infer(&[
+                &["1", "1.5", "true", "x", ""],
+                &[" -2 ", "3", "FALSE", "4", ""]
+            ]),
+            [Int, Float, Bool, String, Int]
+        );
+        // 前导零和超出 i64 范围的整数保留为字符串，数字 1/0 不推断为布尔值
+        assert_eq!(
+            infer(&[
+                &["007", "12345678901234567890", "1", "1e3"],
+                &["7", "1", "0", "-0.5"]
+            ]),
+            [String, String, Int, Float]
+        );
+        assert_eq!(
+            infer(&[&["NaN", "inf", "0"], &["1", "2", "0.0"]]),
+            [String, String, Float]
+        );
+    }
+
+    #[test]
+    fn parses_typed_values() {
+        use ColumnType::*;
+        assert_eq!(parse_typed(Int, " 42 ").unwrap(), json!(42));
+        assert_eq!(parse_typed(Float, "2.5").unwrap(), json!(2.5));
+        assert_eq!(parse_typed(Bool, "Yes").unwrap(), json!(true));
+        assert_eq!(parse_typed(Bool, "0").unwrap(), json!(false));
+        assert_eq!(parse_typed(String, " a ").unwrap(), json!(" a "));
+        assert_eq!(
+            parse_typed(Date, "2024/03/01").unwrap(),
+            json!("2024-03-01")
+        );
+        assert_eq!(
+            parse_typed(Date, "01.03.2024").unwrap(),
+            json!("2024-03-01")
+        );
+        assert_eq!(
+            parse_typed(Date, "Mar 1, 2024").unwrap(),
+            json!("2024-03-01")
+        );
+        assert!(parse_typed(Int, "1.5").is_err());
+        assert!(parse_typed(Float, "inf").is_err());
+        assert!(parse_typed(Bool, "maybe").is_err());
+        assert!(parse_typed(Date, "2024-13-01").is_err());
+    }
+
+    #[test]
+    fn converts_records() {
+        let header = StringRecord::from(vec!["id", "name", "when"]);
+        let overrides = [("when".to_string(), ColumnType::Date)];
+        let record = StringRecord::from(vec!["1", "", "2024-03-01"]);
+
+        // 未推断时空字符串保留，推断后为 null
+        let plain = RecordConverter::new(header.clone(), None, &overrides).unwrap();
+        assert_eq!(
+            plain.convert(&record).unwrap(),
+            json!({"id": "1", "name": "", "when": "2024-03-01"})
+        );
+        let inferred = vec![ColumnType::Int, ColumnType::String, ColumnType::String];
+        let typed = RecordConverter::new(header.clone(), Some(inferred), &overrides).unwrap();
+        assert_eq!(
+            typed.convert(&record).unwrap(),
+            json!({"id": 1, "name": null, "when": "2024-03-01"})
+        );
+        let err = typed
+            .convert(&StringRecord::from(vec!["x", "", ""]))
+            .unwrap_err();
+        assert!(err.to_string().contains("column `id`"));
+
+        let unknown = [("missing".to_string(), ColumnType::Int)];
+        assert!(RecordConverter::new(header, None, &unknown).is_err());
+    }
+
+    #[test]
+    fn converts_fields() {
+        let records = [
+            json!({"id": "1", "score": "2.5", "tags": ["a"]}),
+            json!({"id": "2", "score": "", "extra": "007"}),
+        ];
+        let mut inference = FieldInference::default();
+        records.iter().for_each(|record| inference.observe(record));
+        let types = inference.finish();
+        assert_eq!(types["id"], ColumnType::Int);
+        assert_eq!(types["score"], ColumnType::Float);
+        assert_eq!(types["extra"], ColumnType::String);
+
+        // 只转换字符串值，数组等保持原样
+        let mut converter = FieldConverter::new(Some(types), &[]).unwrap();
+        assert_eq!(
+            converter.convert(records[0].clone()).unwrap(),
+            json!({"id": 1, "score": 2.5, "tags": ["a"]})
+        );
+        assert_eq!(
+            converter.convert(records[1].clone()).unwrap(),
+            json!({"id": 2, "score": null, "extra": "007"})
+        );
+
+        // 未推断时 `--types` 的列在第一条记录中检查
+        let overrides = [("missing".to_string(), ColumnType::Int)];
+        let mut converter = FieldConverter::new(None, &overrides).unwrap();
+        assert!(converter.convert(records[0].clone()).is_err());
+        let overrides = [("id".to_string(), ColumnType::Int)];
+        assert!(FieldConverter::new(Some(HashMap::new()), &overrides).is_err());
+    }
+}
@@ -0,0 +1,341 @@
+use anyhow::{anyhow, Result};
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+
+use super::csv_convert::writer_config;
+use super::filter::Filter;
+use super::nested::unflatten;
+use super::reader::{input_format, missing_value, read_opts_for, read_records, should_nest};
+use super::transform::Projection;
+use super::writer::{new_writer, RecordWriter};
+use crate::opts::{CsvJoinOpts, CsvReadOpts, InputFormat, JoinKind, OutputFormat};
+use crate::utils::{create_output, spool_stdin, stdout_closed};
+
+/// 右表与左表列名冲突时加的后缀
+const RIGHT_SUFFIX: &str = "_right";
+
+/// 按键列连接两个文件
+///
+/// 右表整个读入内存并按键分组，左表逐行读取并与同键的每一行组合，多对多时输出所有组合。
+/// 输出左表的全部列，再加上右表除键列以外的列；键为空或 null 的行不与任何行匹配。
+/// `--where`、`--select` 等作用于连接后的记录
+pub fn process_csv_join(opts: &CsvJoinOpts) -> Result<()> {
+    let output = opts.output.as_deref().unwrap_or("-");
+    let format = OutputFormat::resolve_or(opts.format, output, OutputFormat::Csv)?;
+    if opts.read.schema.is_some() || opts.read.rejects.is_some() {
+        return Err(anyhow!("--schema and --rejects cannot be used with join"));
+    }
+    let (left_keys, right_keys): (Vec<String>, Vec<String>) = opts.on.iter().cloned().unzip();
+
+    // 按 CSV 中的原始列名连接，过滤和列投影留到连接之后
+    let read = CsvReadOpts {
+        flat: true,
+        filter: None,
+        select: None,
+        exclude: Vec::new(),
+        rename: Vec::new(),
+        ..opts.read.clone()
+    };
+    let side = |input: &str| read_opts_for(input, &read, format);
+    let missing = missing_value(opts.read.infer || format.is_columnar());
+
+    let mut right_rows: Vec<Map<String, Value>> = Vec::new();
+    let mut right_columns: Vec<String> = Vec::new();
+    let mut index: HashMap<Vec<String>, Vec<usize>> = HashMap::new();
+    read_records(&opts.right, &side(&opts.right), opts.force, |record| {
+        let map = into_map(record, right_rows.len())?;
+        add_columns(&mut right_columns, &map);
+        if let Some(key) = join_key(&map, &right_keys)? {
+            index.entry(key).or_default().push(right_rows.len());
+        }
+        right_rows.push(map);
+        Ok(())
+    })
+    .map_err(|e| anyhow!("{}: {:#}", opts.right, e))?;
+    let mut matched = vec![false; right_rows.len()];
+
+    let (out, file) = create_output(output, opts.force)?;
+    let mut out = JoinOutput {
+        filter: opts.read.filter.as_deref().map(Filter::parse).transpose()?,
+        projection: Projection::new(&opts.read),
+        nest: should_nest(&opts.left, &opts.read),
+        writer: new_writer(format, out, &writer_config(&opts.left, &opts.read))?,
+    };
+
+    // 右表列在输出中的名字要避开左表的全部列名。CSV/Excel 每行的列都相同，取第一行即可；
+    // JSON、XML 等输入各条记录的列可能不同，先扫描一遍左表，标准输入先转存到临时文件
+    let left_read = side(&opts.left);
+    let spooled = match opts.left.as_str() {
+        "-" if !is_tabular(&opts.left, &left_read) => Some(spool_stdin()?),
+        _ => None,
+    };
+    let left_input = match &spooled {
+        Some(file) => file.path().to_string_lossy().into_owned(),
+        None => opts.left.clone(),
+    };
+    let mut left_columns: Vec<String> = Vec::new();
+    if !is_tabular(&left_input, &left_read) {
+        let mut count = 0;
+        read_records(&left_input, &left_read, opts.force, |record| {
+            add_columns(&mut left_columns, &into_map(record, count)?);
+            count += 1;
+            Ok(())
+        })
+        .map_err(|e| anyhow!("{}: {:#}", opts.left, e))?;
+    }
+
+    let mut right_names: Option<Vec<(String, String)>> = None;
+    let mut count = 0;
+    read_records(&left_input, &left_read, opts.force, |record| {
+        let left = into_map(record, count)?;
+        count += 1;
+        add_columns(&mut left_columns, &left);
+        let names = right_names
+            .get_or_insert_with(|| output_names(&right_columns, &right_keys, &left_columns));
+
+        let rows = match join_key(&left, &left_keys)? {
+            Some(key) => index.get(&key).map(Vec::as_slice).unwrap_or_default(),
+            None => &[],
+        };
+        if rows.is_empty() {
+            if matches!(opts.how, JoinKind::Left | JoinKind::Full) {
+                out.write(merge(left, None, names, &missing))?;
+            }
+            return Ok(());
+        }
+        for &row in rows {
+            matched[row] = true;
+            out.write(merge(left.clone(), Some(&right_rows[row]), names, &missing))?;
+        }
+        Ok(())
+    })
+    // 左表的记录边读边写，写出时的管道关闭保持原样，由 main 正常退出
+    .map_err(|e| {
+        if stdout_closed() {
+            e
+        } else {
+            anyhow!("{}: {:#}", opts.left, e)
+        }
+    })?;
+
+    if matches!(opts.how, JoinKind::Right | JoinKind::Full) {
+        // 左表为空时只有键列
+        if left_columns.is_empty() {
+            left_columns = left_keys.clone();
+        }
+        let names =
+            right_names.unwrap_or_else(|| output_names(&right_columns, &right_keys, &left_columns));
+        for (row, right) in right_rows.iter().enumerate() {
+            if matched[row] {
+                continue;
+            }
+            // 没有匹配的右表行，左表的键列取右表键列的值，其余左表列为空
+            let left = left_columns
+                .iter()
+                .map(|column| {
+                    let value = match left_keys.iter().position(|key| key == column) {
+                        Some(i) => right.get(&right_keys[i]).cloned(),
+                        None => None,
+                    };
+                    (column.clone(), value.unwrap_or_else(|| missing.clone()))
+                })
+                .collect();
+            out.write(merge(left, Some(right), &names, &missing))?;
+        }
+    }
+    out.writer.finish()?;
+    file.commit()
+}
+
+/// 连接后的记录依次经过过滤、列投影和嵌套结构还原后写出
+struct JoinOutput {
+    filter: Option<Filter>,
+    projection: Projection,
+    nest: bool,
+    writer: Box<dyn RecordWriter>,
+}
+
+impl JoinOutput {
+    fn write(&mut self, record: Map<String, Value>) -> Result<()> {
+        let record = Value::Object(record);
+        if let Some(filter) = &mut self.filter {
+            if !filter.matches(&record)? {
+                return Ok(());
+            }
+        }
+        let record = self.projection.apply(record)?;
+        self.writer.write_record(&if self.nest {
+            unflatten(record)?
+        } else {
+            record
+        })
+    }
+}
+
+fn is_tabular(input: &str, read: &CsvReadOpts) -> bool {
+    matches!(
+        input_format(input, read),
+        InputFormat::Csv | InputFormat::Excel
+    )
+}
+
+/// 按出现顺序记录列名
+fn add_columns(columns: &mut Vec<String>, record: &Map<String, Value>) {
+    for key in record.keys() {
+        if !columns.contains(key) {
+            columns.push(key.clone());
+        }
+    }
+}
+
+fn into_map(record: Value, index: usize) -> Result<Map<String, Value>> {
+    match record {
+        Value::Object(map) => Ok(map),
+        _ => Err(anyhow!("Record {} is not an object", index + 1)),
+    }
+}
+
+/// 记录中键列的值；任一键列为空或 null 时返回 `None`，即不参与匹配
+///
+/// 键按文本比较，因此推断类型后的 `7` 与另一边未推断的 `"7"` 仍然匹配
+fn join_key(record: &Map<String, Value>, keys: &[String]) -> Result<Option<Vec<String>>> {
+    let mut values = Vec::with_capacity(keys.len());
+    for key in keys {
+        match record.get(key) {
+            None => return Err(anyhow!("Join column `{}` not found", key)),
+            Some(Value::Null) => return Ok(None),
+            Some(Value::String(s)) if s.is_empty() => return Ok(None),
+            Some(Value::String(s)) => values.push(s.clone()),
+            Some(value) => values.push(value.to_string()),
+        }
+    }
+    Ok(Some(values))
+}
+
+/// 右表除键列以外的列，与左表列名相同时加 `_right` 后缀
+fn output_names(
+    right_columns: &[String],
+    right_keys: &[String],
+    left_columns: &[String],
+) -> Vec<(String, String)> {
+    let mut taken: HashSet<String> = left_columns.iter().cloned().collect();
+    let mut names = Vec::new();
+    for column in right_columns.iter().filter(|c| !right_keys.contains(c)) {
+        let mut name = column.clone();
+        while taken.contains(&name) {
+            name.push_str(RIGHT_SUFFIX);
+        }
+        taken.insert(name.clone());
+        names.push((column.clone(), name));
+    }
+    names
+}
+
+fn merge(
+    mut left: Map<String, Value>,
+    right: Option<&Map<String, Value>>,
+    names: &[(String, String)],
+    missing: &Value,
+) -> Map<String, Value> {
+    for (column, name) in names {
+        let value = right
+            .and_then(|right| right.get(column))
+            .cloned()
+            .unwrap_or_else(|| missing.clone());
+        left.insert(name.clone(), value);
+    }
+    left
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use std::fs;
+    use tempfile::TempDir;
+
+    /// 在临时目录中写入左右两表并连接，返回 NDJSON 输出
+    fn join(left: (&str, &str), right: (&str, &str), args: &[&str]) -> Vec<Value> {
+        let dir = TempDir::new().unwrap();
+        let path = |name: &str| dir.path().join(name).to_string_lossy().into_owned();
+        fs::write(path(left.0), left.1).unwrap();
+        fs::write(path(right.0), right.1).unwrap();
+        let output = path("out.ndjson");
+        let mut argv = vec![
+            "join".to_string(),
+            "--left".to_string(),
+            path(left.0),
+            "--right".to_string(),
+            path(right.0),
+            "-o".to_string(),
+            output.clone(),
+        ];
+        argv.extend(args.iter().map(|arg| arg.to_string()));
+        process_csv_join(&CsvJoinOpts::parse_from(argv)).unwrap();
+        fs::read_to_string(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    const LEFT: &str = "id,name\n1,a\n2,b\n,c\n";
+    const RIGHT: &str = "key,score\n1,10\n1,11\n3,30\n,40\n";
+
+    #[test]
+    fn joins_by_kind() {
+        let ids = |how: &str| {
+            join(
+                ("l.csv", LEFT),
+                ("r.csv", RIGHT),
+                &["--on", "id=key", "--how", how],
+            )
+            .iter()
+            .map(|r| format!("{}:{}", r["name"], r["score"]))
+            .collect::<Vec<_>>()
+            .join(" ")
+        };
+        // 多对多输出所有组合，空键不与任何行匹配
+        assert_eq!(ids("inner"), r#""a":"10" "a":"11""#);
+        assert_eq!(ids("left"), r#""a":"10" "a":"11" "b":"" "c":"""#);
+        assert_eq!(ids("right"), r#""a":"10" "a":"11" "":"30" "":"40""#);
+        assert_eq!(
+            ids("full"),
+            r#""a":"10" "a":"11" "b":"" "c":"" "":"30" "":"40""#
+        );
+        // 没有匹配的右表行，左表的键列取右表键列的值
+        let rows = join(
+            ("l.csv", LEFT),
+            ("r.csv", RIGHT),
+            &["--on", "id=key", "--how", "right"],
+        );
+        assert_eq!(rows[2]["id"], "3");
+        assert!(rows[2].get("key").is_none());
+    }
+
+    #[test]
+    fn renames_colliding_right_columns() {
+        let rows = join(
+            ("l.csv", "id,v,v_right\n1,a,b\n"),
+            ("r.csv", "id,v\n1,c\n"),
+            &["--on", "id"],
+        );
+        assert_eq!(rows[0]["v"], "a");
+        assert_eq!(rows[0]["v_right"], "b");
+        assert_eq!(rows[0]["v_right_right"], "c");
+    }
+
+    #[test]
+    fn collisions_consider_all_left_records() {
+        // 左表第二条记录才出现的列同样不能被右表覆盖，且所有输出行的列名一致
+        let rows = join(
+            ("l.ndjson", "{\"id\": 1}\n{\"id\": 2, \"v\": \"left\"}\n"),
+            ("r.csv", "id,v\n1,r1\n2,r2\n"),
+            &["--on", "id"],
+        );
+        assert_eq!(rows[0]["v_right"], "r1");
+        assert!(rows[0].get("v").is_none());
+        assert_eq!(rows[1]["v"], "left");
+        assert_eq!(rows[1]["v_right"], "r2");
+    }
+}
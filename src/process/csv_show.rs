@@ -0,0 +1,264 @@
+use anyhow::Result;
+use std::{
+    collections::VecDeque,
+    io::{self, IsTerminal, Write},
+};
+use terminal_size::{terminal_size, Width};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use super::nested::flatten;
+use super::reader::read_records;
+use crate::opts::CsvShowOpts;
+use crate::utils::get_writer;
+
+type Row = Vec<(String, String)>;
+
+/// 待显示的数据：`None` 表示 `--head` 与 `--tail` 之间省略的行
+struct Table {
+    columns: Vec<String>,
+    rows: Vec<Option<Vec<String>>>,
+    total: usize,
+}
+
+/// 以对齐的表格形式在终端中显示数据
+pub fn process_csv_show(opts: &CsvShowOpts) -> Result<()> {
+    let table = collect_table(opts)?;
+    let width = opts.width.or_else(|| {
+        // 输出到管道时不分页
+        io::stdout()
+            .is_terminal()
+            .then(terminal_size)
+            .flatten()
+            .map(|(Width(w), _)| w as usize)
+    });
+
+    let mut writer = get_writer("-")?;
+    render(&table, opts.max_width, width, &mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn collect_table(opts: &CsvShowOpts) -> Result<Table> {
+    let mut head: Vec<Row> = Vec::new();
+    let mut tail: VecDeque<Row> = VecDeque::new();
+    let mut total = 0;
+
+    read_records(&opts.input, &opts.read, false, |record| {
+        total += 1;
+        let mut row = Vec::new();
+        flatten(&record, String::new(), &mut row);
+
+        match (opts.head, opts.tail) {
+            (Some(n), _) if head.len() < n => head.push(row),
+            (_, Some(n)) => {
+                // 只保留最后 n 行，内存占用与文件大小无关
+                if tail.len() == n {
+                    tail.pop_front();
+                }
+                if n > 0 {
+                    tail.push_back(row);
+                }
+            }
+            (Some(_), None) => {}
+            (None, None) => head.push(row),
+        }
+        Ok(())
+    })?;
+
+    let mut columns: Vec<String> = Vec::new();
+    for (name, _) in head.iter().chain(tail.iter()).flatten() {
+        if !columns.contains(name) {
+            columns.push(name.clone());
+        }
+    }
+
+    let to_cells = |row: Row| {
+        columns
+            .iter()
+            .map(|column| {
+                row.iter()
+                    .find(|(name, _)| name == column)
+                    .map(|(_, value)| value.clone())
+                    .unwrap_or_default()
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let skipped = head.len() + tail.len() < total;
+    let mut rows: Vec<_> = head.into_iter().map(|row| Some(to_cells(row))).collect();
+    if skipped && opts.tail.is_some() {
+        rows.push(None);
+    }
+    rows.extend(tail.into_iter().map(|row| Some(to_cells(row))));
+
+    Ok(Table {
+        columns,
+        rows,
+        total,
+    })
+}
+
+fn render(
+    table: &Table,
+    max_width: usize,
+    width: Option<usize>,
+    out: &mut impl Write,
+) -> Result<()> {
+    let header: Vec<String> = table
+        .columns
+        .iter()
+        .map(|c| truncate(c, max_width))
+        .collect();
+    let rows: Vec<Option<Vec<String>>> = table
+        .rows
+        .iter()
+        .map(|row| {
+            row.as_ref()
+                .map(|cells| cells.iter().map(|c| truncate(c, max_width)).collect())
+        })
+        .collect();
+
+    let widths: Vec<usize> = (0..header.len())
+        .map(|i| {
+            rows.iter()
+                .flatten()
+                .map(|cells| cells[i].width())
+                .chain([header[i].width(), 1])
+                .max()
+                .unwrap_or(1)
+        })
+        .collect();
+    // 整列都是数字时右对齐
+    let numeric: Vec<bool> = (0..header.len())
+        .map(|i| {
+            let mut cells = rows.iter().flatten().map(|cells| &cells[i]);
+            cells.clone().any(|c| !c.is_empty())
+                && cells.all(|c| c.is_empty() || c.trim().parse::<f64>().is_ok())
+        })
+        .collect();
+
+    // 没有任何记录时无法确定列，只输出行数
+    let pages = if header.is_empty() {
+        Vec::new()
+    } else {
+        paginate(&widths, width)
+    };
+    for (i, page) in pages.iter().enumerate() {
+        if pages.len() > 1 {
+            if i > 0 {
+                writeln!(out)?;
+            }
+            // 重复显示的第一列不计入本页的列范围
+            let first = if i > 0 && page.len() > 1 {
+                page[1]
+            } else {
+                page[0]
+            };
+            writeln!(
+                out,
+                "Columns {}-{} of {}",
+                first + 1,
+                page.last().map_or(0, |c| c + 1),
+                widths.len()
+            )?;
+        }
+
+        writeln!(out, "{}", border(page, &widths, '┌', '┬', '┐'))?;
+        writeln!(out, "{}", line(page, &widths, &[], |c| &header[c]))?;
+        writeln!(out, "{}", border(page, &widths, '├', '┼', '┤'))?;
+        for row in &rows {
+            match row {
+                Some(cells) => writeln!(out, "{}", line(page, &widths, &numeric, |c| &cells[c]))?,
+                None => writeln!(out, "{}", line(page, &widths, &[], |_| "…"))?,
+            }
+        }
+        writeln!(out, "{}", border(page, &widths, '└', '┴', '┘'))?;
+    }
+
+    let shown = rows.iter().flatten().count();
+    if shown < table.total {
+        writeln!(out, "{} of {} rows", shown, table.total)?;
+    } else {
+        writeln!(out, "{} rows", table.total)?;
+    }
+    Ok(())
+}
+
+/// 按可用宽度把列分成多页，第一列作为标识列在每一页重复显示
+fn paginate(widths: &[usize], width: Option<usize>) -> Vec<Vec<usize>> {
+    // 每列占用 `│ ` + 内容 + ` `，最后再加一个 `│`
+    let cost = |c: usize| widths[c] + 3;
+    let Some(width) = width else {
+        return vec![(0..widths.len()).collect()];
+    };
+
+    let mut pages: Vec<Vec<usize>> = Vec::new();
+    let mut page: Vec<usize> = Vec::new();
+    let mut used = 1;
+    for c in 0..widths.len() {
+        if !page.is_empty() && used + cost(c) > width {
+            pages.push(std::mem::take(&mut page));
+            used = 1;
+            if 1 + cost(0) + cost(c) <= width {
+                page.push(0);
+                used += cost(0);
+            }
+        }
+        page.push(c);
+        used += cost(c);
+    }
+    if !page.is_empty() || pages.is_empty() {
+        pages.push(page);
+    }
+    pages
+}
+
+fn border(page: &[usize], widths: &[usize], left: char, mid: char, right: char) -> String {
+    let segments: Vec<String> = page.iter().map(|&c| "─".repeat(widths[c] + 2)).collect();
+    format!("{}{}{}", left, segments.join(&mid.to_string()), right)
+}
+
+fn line<'a>(
+    page: &[usize],
+    widths: &[usize],
+    numeric: &[bool],
+    cell: impl Fn(usize) -> &'a str,
+) -> String {
+    let cells: Vec<String> = page
+        .iter()
+        .map(|&c| {
+            let text = cell(c);
+            let pad = " ".repeat(widths[c] - text.width());
+            if numeric.get(c).copied().unwrap_or(false) {
+                format!(" {}{} ", pad, text)
+            } else {
+                format!(" {}{} ", text, pad)
+            }
+        })
+        .collect();
+    format!("│{}│", cells.join("│"))
+}
+
+/// 按显示宽度截断单元格，控制字符替换为空格以免破坏对齐；`max_width` 为 0 表示不截断
+fn truncate(text: &str, max_width: usize) -> String {
+    let text: String = text
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+    if max_width == 0 || text.width() <= max_width {
+        return text;
+    }
+
+    let mut ret = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let w = c.width().unwrap_or(0);
+        if used + w + 1 > max_width {
+            break;
+        }
+        ret.push(c);
+        used += w;
+    }
+    ret.push('…');
+    ret
+}
@@ -0,0 +1,128 @@
+use anyhow::Result;
+use encoding_rs::{Encoding, GB18030, UTF_16BE, UTF_16LE};
+use encoding_rs_io::DecodeReaderBytesBuilder;
+use std::io::{Cursor, Read};
+
+use crate::utils::get_reader;
+
+/// 用于判断编码的文件开头的字节数
+const SNIFF_LEN: usize = 64 * 1024;
+/// 判断为 GB18030 至少需要的双字节字符数
+const MIN_GBK_PAIRS: usize = 2;
+
+/// 打开输入并转码为 UTF-8，开头的 BOM 总是被去掉
+///
+/// `encoding` 为 `None` 时自动判断：有 BOM 时按 BOM，否则依次尝试无 BOM 的
+/// UTF-16、UTF-8 和 GB18030（兼容 GBK），都不符合时仍按 UTF-8 读取
+pub(crate) fn decode_reader(input: &str, encoding: Option<&'static Encoding>) -> Result<impl Read> {
+    let mut reader = get_reader(input)?;
+    // 标准输入只能读一次，先读出开头用于判断，再与剩余部分拼接
+    let mut prefix = Vec::new();
+    (&mut reader)
+        .take(SNIFF_LEN as u64)
+        .read_to_end(&mut prefix)?;
+    let encoding = encoding.or_else(|| sniff(&prefix));
+
+    Ok(DecodeReaderBytesBuilder::new()
+        .encoding(encoding)
+        // UTF-8 输入原样传递，非法的字节交给 `--on-error` 处理而不是替换为 U+FFFD
+        .utf8_passthru(true)
+        .strip_bom(true)
+        .build(Cursor::new(prefix).chain(reader)))
+}
+
+fn sniff(prefix: &[u8]) -> Option<&'static Encoding> {
+    if Encoding::for_bom(prefix).is_some() {
+        return None;
+    }
+    // 截到最后一个换行，避免末尾被截断的多字节字符影响判断
+    let prefix = match prefix.iter().rposition(|&b| b == b'\n') {
+        Some(end) if prefix.len() == SNIFF_LEN => &prefix[..end],
+        _ => prefix,
+    };
+
+    // 没有 BOM 的 UTF-16 中，ASCII 字符（分隔符、换行、数字）的另一半字节为 0，
+    // 而 CJK 字符两个字节都很少为 0
+    let zeros = |parity: usize| {
+        prefix
+            .iter()
+            .skip(parity)
+            .step_by(2)
+            .filter(|&&b| b == 0)
+            .count()
+    };
+    let (even, odd) = (zeros(0), zeros(1));
+    let units = prefix.len() / 2;
+    if odd > units / 4 && even <= odd / 8 {
+        return Some(UTF_16LE);
+    }
+    if even > units / 4 && odd <= even / 8 {
+        return Some(UTF_16BE);
+    }
+    if std::str::from_utf8(prefix).is_ok() || !looks_like_gbk(prefix) {
+        return None;
+    }
+    GB18030
+        .decode_without_bom_handling_and_without_replacement(prefix)
+        .map(|_| GB18030)
+}
+
+/// 只有确实像双字节中文时才按 GB18030 读取，否则按 UTF-8 读取，非法的行交给 `--on-error` 处理
+///
+/// GBK 的汉字两个字节通常都不小于 0x80；Latin-1 等单字节编码的高位字节前后多是 ASCII，
+/// 如 `Ren\xe9e` 虽然也能按 GB18030 解码，但不会形成两个字节都是高位的字节对
+fn looks_like_gbk(bytes: &[u8]) -> bool {
+    let (mut pairs, mut weak) = (0, 0);
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        if b < 0x80 {
+            i += 1;
+            continue;
+        }
+        match bytes.get(i + 1) {
+            Some(0x80..=0xFE) if (0x81..=0xFE).contains(&b) => pairs += 1,
+            // 合法的 GBK 扩展字符，但更可能是单字节编码中紧挨 ASCII 的高位字节
+            Some(0x40..=0x7E) if (0x81..=0xFE).contains(&b) => weak += 1,
+            _ => return false,
+        }
+        i += 2;
+    }
+    pairs >= MIN_GBK_PAIRS && weak * 4 <= pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// encoding_rs 不能编码为 UTF-16，单独处理
+    fn encode(text: &str, encoding: &'static Encoding) -> Vec<u8> {
+        if encoding == UTF_16LE {
+            text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+        } else if encoding == UTF_16BE {
+            text.encode_utf16().flat_map(u16::to_be_bytes).collect()
+        } else {
+            encoding.encode(text).0.into_owned()
+        }
+    }
+
+    #[test]
+    fn sniff_encodings() {
+        let text = "姓名,年龄\n张三,18\n李四,20\n";
+        assert_eq!(sniff(text.as_bytes()), None);
+        assert_eq!(sniff(&encode(text, GB18030)), Some(GB18030));
+        assert_eq!(sniff(&encode(text, UTF_16LE)), Some(UTF_16LE));
+        assert_eq!(sniff(&encode(text, UTF_16BE)), Some(UTF_16BE));
+        // Latin-1 等单字节编码中孤立的高位字节不当作 GB18030
+        assert_eq!(sniff(b"name,city\nRen\xe9e,Paris\n"), None);
+        assert_eq!(
+            sniff(b"name,city\nRen\xe9e,Z\xfcrich\nJos\xe9,M\xe1laga\n"),
+            None
+        );
+        assert_eq!(sniff(&encode("name\n王\n", GB18030)), None);
+        // 有 BOM 时由解码器按 BOM 处理
+        let mut bom = vec![0xFF, 0xFE];
+        bom.extend(encode(text, UTF_16LE));
+        assert_eq!(sniff(&bom), None);
+    }
+}
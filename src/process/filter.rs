@@ -0,0 +1,494 @@
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use serde_json::{Number, Value};
+use std::cmp::Ordering;
+
+use super::nested::{lookup, ColumnCheck};
+
+/// `--where` 过滤表达式
+///
+/// 语法示例：`Nationality == "Italy" && "Kit Number" < 20`
+/// - 列名可以直接书写，或用反引号包裹；比较运算符左侧的双引号字符串也视为列名；
+///   嵌套字段用 `address.city`、`tags[0]` 访问
+/// - 字面量：`'单引号字符串'`、比较运算符右侧的 `"双引号字符串"`、数字、`true`、`false`、`null`
+/// - 比较：`==` `!=` `<` `<=` `>` `>=`，`contains` 包含子串，`=~` / `!~` 正则匹配
+/// - 组合：`&&` / `and`、`||` / `or`、`!` / `not`，以及括号
+#[derive(Debug)]
+pub(crate) struct Filter {
+    expr: Expr,
+    columns: ColumnCheck,
+}
+
+#[derive(Debug)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Operand, CmpOp, Operand),
+    Contains(Operand, Operand),
+    Matches(Operand, Regex),
+    Truthy(Operand),
+}
+
+#[derive(Debug)]
+enum Operand {
+    Column(String),
+    Literal(Value),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+    Cmp(CmpOp),
+    Contains,
+    Match,
+    NotMatch,
+    Ident(String),
+    Column(String),
+    SingleQuoted(String),
+    DoubleQuoted(String),
+    Number(Number),
+}
+
+impl Filter {
+    pub fn parse(input: &str) -> Result<Self> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.parse_or()?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            return Err(anyhow!(
+                "Invalid --where expression: unexpected {:?}",
+                token
+            ));
+        }
+        let mut names = Vec::new();
+        expr.columns(&mut names);
+        Ok(Self {
+            expr,
+            columns: ColumnCheck::default().paths("--where", names),
+        })
+    }
+
+    pub fn matches(&mut self, record: &Value) -> Result<bool> {
+        self.columns.check(record)?;
+        Ok(self.expr.eval(record))
+    }
+}
+
+impl Expr {
+    fn eval(&self, record: &Value) -> bool {
+        match self {
+            Expr::Or(a, b) => a.eval(record) || b.eval(record),
+            Expr::And(a, b) => a.eval(record) && b.eval(record),
+            Expr::Not(a) => !a.eval(record),
+            Expr::Compare(lhs, op, rhs) => {
+                let ord = compare(lhs.resolve(record), rhs.resolve(record));
+                match op {
+                    CmpOp::Eq => ord == Some(Ordering::Equal),
+                    CmpOp::Ne => ord != Some(Ordering::Equal),
+                    CmpOp::Lt => ord == Some(Ordering::Less),
+                    CmpOp::Le => matches!(ord, Some(Ordering::Less | Ordering::Equal)),
+                    CmpOp::Gt => ord == Some(Ordering::Greater),
+                    CmpOp::Ge => matches!(ord, Some(Ordering::Greater | Ordering::Equal)),
+                }
+            }
+            Expr::Contains(lhs, rhs) => {
+                match (text(lhs.resolve(record)), text(rhs.resolve(record))) {
+                    (Some(haystack), Some(needle)) => haystack.contains(needle.as_str()),
+                    _ => false,
+                }
+            }
+            Expr::Matches(lhs, re) => text(lhs.resolve(record)).is_some_and(|s| re.is_match(&s)),
+            Expr::Truthy(operand) => match operand.resolve(record) {
+                Value::Null => false,
+                Value::Bool(b) => *b,
+                Value::Number(n) => n.as_f64() != Some(0.0),
+                Value::String(s) => !s.is_empty() && !s.eq_ignore_ascii_case("false"),
+                _ => true,
+            },
+        }
+    }
+
+    /// 表达式中引用的列名
+    fn columns(&self, names: &mut Vec<String>) {
+        match self {
+            Expr::Or(a, b) | Expr::And(a, b) => {
+                a.columns(names);
+                b.columns(names);
+            }
+            Expr::Not(a) => a.columns(names),
+            Expr::Compare(lhs, _, rhs) | Expr::Contains(lhs, rhs) => {
+                lhs.column(names);
+                rhs.column(names);
+            }
+            Expr::Matches(operand, _) | Expr::Truthy(operand) => operand.column(names),
+        }
+    }
+}
+
+impl Operand {
+    fn resolve<'a>(&'a self, record: &'a Value) -> &'a Value {
+        match self {
+            Operand::Column(name) => lookup(record, name).unwrap_or(&Value::Null),
+            Operand::Literal(value) => value,
+        }
+    }
+
+    fn column(&self, names: &mut Vec<String>) {
+        if let Operand::Column(name) = self {
+            names.push(name.clone());
+        }
+    }
+}
+
+/// 任一侧为数字时按数值比较（字符串会尝试解析为数字），否则按字符串比较
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Null, Value::Null) => Some(Ordering::Equal),
+        (Value::Null, _) | (_, Value::Null) => None,
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        (Value::Number(_), _) | (_, Value::Number(_)) => number(a)?.partial_cmp(&number(b)?),
+        _ => None,
+    }
+}
+
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn text(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let token = match (c, next) {
+            _ if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            ('(', _) => Token::LParen,
+            (')', _) => Token::RParen,
+            ('&', Some('&')) => Token::And,
+            ('|', Some('|')) => Token::Or,
+            ('=', Some('=')) => Token::Cmp(CmpOp::Eq),
+            ('=', Some('~')) => Token::Match,
+            ('!', Some('=')) => Token::Cmp(CmpOp::Ne),
+            ('!', Some('~')) => Token::NotMatch,
+            ('!', _) => Token::Not,
+            ('<', Some('=')) => Token::Cmp(CmpOp::Le),
+            ('<', _) => Token::Cmp(CmpOp::Lt),
+            ('>', Some('=')) => Token::Cmp(CmpOp::Ge),
+            ('>', _) => Token::Cmp(CmpOp::Gt),
+            ('"' | '\'' | '`', _) => {
+                let (s, end) = read_quoted(&chars, i)?;
+                i = end;
+                tokens.push(match c {
+                    '"' => Token::DoubleQuoted(s),
+                    '\'' => Token::SingleQuoted(s),
+                    _ => Token::Column(s),
+                });
+                continue;
+            }
+            _ if c.is_ascii_digit() || (c == '-' && next.is_some_and(|n| n.is_ascii_digit())) => {
+                let start = i;
+                i += 1;
+                // 指数部分可以带符号，如 `1e-5`
+                while i < chars.len()
+                    && (chars[i].is_ascii_alphanumeric()
+                        || chars[i] == '.'
+                        || (matches!(chars[i], '+' | '-') && matches!(chars[i - 1], 'e' | 'E')))
+                {
+                    i += 1;
+                }
+                let s: String = chars[start..i].iter().collect();
+                tokens.push(Token::Number(parse_number(&s)?));
+                continue;
+            }
+            _ if c.is_alphanumeric() || c == '_' => {
+                let start = i;
+                while i < chars.len()
+                    && (chars[i].is_alphanumeric() || matches!(chars[i], '_' | '.' | '[' | ']'))
+                {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                tokens.push(match word.as_str() {
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    "not" => Token::Not,
+                    "contains" => Token::Contains,
+                    _ => Token::Ident(word),
+                });
+                continue;
+            }
+            _ => {
+                return Err(anyhow!(
+                    "Invalid --where expression: unexpected `{}` at position {}",
+                    c,
+                    i + 1
+                ))
+            }
+        };
+
+        // 双字符运算符
+        i += match token {
+            Token::And
+            | Token::Or
+            | Token::Match
+            | Token::NotMatch
+            | Token::Cmp(CmpOp::Eq | CmpOp::Ne | CmpOp::Le | CmpOp::Ge) => 2,
+            _ => 1,
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+/// 读取引号包裹的内容，支持 `\` 转义，返回内容与结束位置
+fn read_quoted(chars: &[char], start: usize) -> Result<(String, usize)> {
+    let quote = chars[start];
+    let mut s = String::new();
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if i + 1 < chars.len() => {
+                s.push(chars[i + 1]);
+                i += 2;
+            }
+            c if c == quote => return Ok((s, i + 1)),
+            c => {
+                s.push(c);
+                i += 1;
+            }
+        }
+    }
+    Err(anyhow!(
+        "Invalid --where expression: unterminated {} at position {}",
+        quote,
+        start + 1
+    ))
+}
+
+fn parse_number(s: &str) -> Result<Number> {
+    if let Ok(n) = s.parse::<i64>() {
+        return Ok(n.into());
+    }
+    s.parse::<f64>()
+        .ok()
+        .and_then(Number::from_f64)
+        .ok_or_else(|| anyhow!("Invalid --where expression: bad number `{}`", s))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_not()?;
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_not()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr> {
+        match self.peek() {
+            Some(Token::Not) => {
+                self.pos += 1;
+                Ok(Expr::Not(Box::new(self.parse_not()?)))
+            }
+            Some(Token::LParen) => {
+                self.pos += 1;
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(expr),
+                    _ => Err(anyhow!("Invalid --where expression: expected `)`")),
+                }
+            }
+            _ => self.parse_comparison(),
+        }
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr> {
+        let lhs = self.parse_operand(true)?;
+        let expr = match self.peek() {
+            Some(Token::Cmp(op)) => {
+                let op = *op;
+                self.pos += 1;
+                Expr::Compare(lhs, op, self.parse_operand(false)?)
+            }
+            Some(Token::Contains) => {
+                self.pos += 1;
+                Expr::Contains(lhs, self.parse_operand(false)?)
+            }
+            Some(Token::Match | Token::NotMatch) => {
+                let negate = self.next() == Some(Token::NotMatch);
+                let pattern = match self.next() {
+                    Some(Token::SingleQuoted(s) | Token::DoubleQuoted(s)) => s,
+                    _ => {
+                        return Err(anyhow!(
+                            "Invalid --where expression: expected a regex string after `=~`"
+                        ))
+                    }
+                };
+                let expr = Expr::Matches(lhs, Regex::new(&pattern)?);
+                if negate {
+                    Expr::Not(Box::new(expr))
+                } else {
+                    expr
+                }
+            }
+            _ => Expr::Truthy(lhs),
+        };
+        Ok(expr)
+    }
+
+    /// 运算符左侧的双引号字符串视为列名，右侧视为字面量
+    fn parse_operand(&mut self, lhs: bool) -> Result<Operand> {
+        match self.next() {
+            Some(Token::Ident(word)) => Ok(match word.as_str() {
+                "true" => Operand::Literal(Value::Bool(true)),
+                "false" => Operand::Literal(Value::Bool(false)),
+                "null" => Operand::Literal(Value::Null),
+                _ => Operand::Column(word),
+            }),
+            Some(Token::Column(name)) => Ok(Operand::Column(name)),
+            Some(Token::DoubleQuoted(s)) if lhs => Ok(Operand::Column(s)),
+            Some(Token::DoubleQuoted(s) | Token::SingleQuoted(s)) => {
+                Ok(Operand::Literal(Value::String(s)))
+            }
+            Some(Token::Number(n)) => Ok(Operand::Literal(Value::Number(n))),
+            Some(token) => Err(anyhow!(
+                "Invalid --where expression: unexpected {:?}",
+                token
+            )),
+            None => Err(anyhow!(
+                "Invalid --where expression: unexpected end of input"
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn number(n: f64) -> Token {
+        Token::Number(Number::from_f64(n).unwrap())
+    }
+
+    #[test]
+    fn tokenize_numbers() {
+        assert_eq!(
+            tokenize("a < 1e-5 || b >= -2.5E+3 || c == 42").unwrap(),
+            vec![
+                Token::Ident("a".into()),
+                Token::Cmp(CmpOp::Lt),
+                number(1e-5),
+                Token::Or,
+                Token::Ident("b".into()),
+                Token::Cmp(CmpOp::Ge),
+                number(-2500.0),
+                Token::Or,
+                Token::Ident("c".into()),
+                Token::Cmp(CmpOp::Eq),
+                Token::Number(42.into()),
+            ]
+        );
+        assert!(tokenize("a == 1e").is_err());
+    }
+
+    #[test]
+    fn tokenize_quotes() {
+        assert_eq!(
+            tokenize(r#"`Kit Number` != 'x' and "Name" contains "o""#).unwrap(),
+            vec![
+                Token::Column("Kit Number".into()),
+                Token::Cmp(CmpOp::Ne),
+                Token::SingleQuoted("x".into()),
+                Token::And,
+                Token::DoubleQuoted("Name".into()),
+                Token::Contains,
+                Token::DoubleQuoted("o".into()),
+            ]
+        );
+        assert!(tokenize("a == 'x").is_err());
+    }
+
+    #[test]
+    fn parse_and_match() {
+        let record = json!({"Name": "Dybala", "Kit Number": "10", "address": {"city": "Turin"}});
+        let cases = [
+            (r#""Kit Number" < 20 && Name == "Dybala""#, true),
+            ("`Kit Number` >= 1e1 and not (Name =~ '^D')", false),
+            ("address.city contains 'ur' || Name == 'x'", true),
+            ("Name !~ 'a$'", false),
+        ];
+        for (expr, expected) in cases {
+            let mut filter = Filter::parse(expr).unwrap();
+            assert_eq!(filter.matches(&record).unwrap(), expected, "{}", expr);
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(Filter::parse("a == ").is_err());
+        assert!(Filter::parse("(a == 1").is_err());
+        assert!(Filter::parse("a == 1 b").is_err());
+        let mut filter = Filter::parse("missing == 1").unwrap();
+        assert!(filter.matches(&json!({"a": 1})).is_err());
+    }
+}
@@ -1,7 +1,10 @@
 use anyhow::{anyhow, Result};
 use rand::seq::{IndexedRandom, SliceRandom};
+use std::io::Write;
 use zxcvbn::zxcvbn;
 
+use crate::utils::create_private_output;
+
 const UPPER: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ";
 const LOWER: &[u8] = b"abcdefghijkmnopqrstuvwxyz";
 const NUMBER: &[u8] = b"123456789";
@@ -171,6 +174,8 @@ pub fn process_genpass(
     lower: bool,
     number: bool,
     symbol: bool,
+    output: &str,
+    force: bool,
 ) -> Result<()> {
     let config = PasswordConfig {
         length,
@@ -186,8 +191,12 @@ pub fn process_genpass(
     // 生成密码
     let password = generate_password(&config)?;
 
-    // 输出结果
-    println!("{}", password);
+    // 输出结果，强度信息写到标准错误，不影响管道中的密码输出
+    let (mut writer, file) = create_private_output(output, force)?;
+    writeln!(writer, "{}", password)?;
+    writer.flush()?;
+    drop(writer);
+    file.commit()?;
     evaluate_password_strength(&password);
 
     Ok(())
@@ -1,5 +1,30 @@
+mod batch;
+mod binary;
+mod columnar;
+mod convert;
+mod csv_concat;
 mod csv_convert;
+mod csv_infer;
+mod csv_join;
+mod csv_show;
+mod encoding;
+mod filter;
 mod gen_pass;
+mod nested;
+mod reader;
+mod rejects;
+mod schema;
+mod sheet;
+mod sort;
+mod transform;
+mod writer;
+mod xml;
 
+pub use batch::process_csv_batch;
+pub use binary::process_decode;
+pub use convert::process_convert;
+pub use csv_concat::process_csv_concat;
 pub use csv_convert::process_csv;
+pub use csv_join::process_csv_join;
+pub use csv_show::process_csv_show;
 pub use gen_pass::process_genpass;
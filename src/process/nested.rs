@@ -0,0 +1,216 @@
+use anyhow::{anyhow, Result};
+use serde_json::{Map, Value};
+
+/// 列名中的一段路径：`address.city` 为两个键，`tags[0]` 为键加下标
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// 把记录展开为 `(列名, 单元格文本)` 列表，嵌套对象用 `a.b`、数组用 `a[0]` 表示
+pub(crate) fn flatten(value: &Value, prefix: String, fields: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let key = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten(value, key, fields);
+            }
+        }
+        Value::Array(items) => {
+            for (i, value) in items.iter().enumerate() {
+                flatten(value, format!("{}[{}]", prefix, i), fields);
+            }
+        }
+        Value::Null => fields.push((prefix, String::new())),
+        Value::String(s) => fields.push((prefix, s.clone())),
+        Value::Bool(_) | Value::Number(_) => fields.push((prefix, value.to_string())),
+    }
+}
+
+/// 按列名中的 `a.b`、`a[0]` 把扁平记录还原为嵌套的对象和数组，是 [`flatten`] 的逆操作
+pub(crate) fn unflatten(record: Value) -> Result<Value> {
+    let Value::Object(map) = record else {
+        return Ok(record);
+    };
+    // 大多数文件没有嵌套列名，直接返回
+    if !map.keys().any(|key| key.contains(['.', '['])) {
+        return Ok(Value::Object(map));
+    }
+
+    // 下标来自表头，可能任意大；一条记录的数组元素不会多于列数，超出的下标按普通列名处理
+    let limit = map.len();
+    let mut root = Value::Object(Map::with_capacity(map.len()));
+    for (key, value) in map {
+        let path = parse_path(&key, limit).unwrap_or_else(|| vec![Segment::Key(key.clone())]);
+        if !insert(&mut root, &path, value) {
+            return Err(anyhow!("Column `{}` conflicts with another column", key));
+        }
+    }
+    Ok(root)
+}
+
+/// 按 `a.b`、`a[0]` 路径查找嵌套记录中的值
+pub(crate) fn lookup<'a>(record: &'a Value, path: &str) -> Option<&'a Value> {
+    if let Some(value) = record.get(path) {
+        return Some(value);
+    }
+    parse_path(path, usize::MAX)?
+        .iter()
+        .try_fold(record, |value, segment| match segment {
+            Segment::Key(key) => value.get(key),
+            Segment::Index(i) => value.get(i),
+        })
+}
+
+/// 参数中引用的列名，以第一条记录的字段为准检查是否存在，避免拼写错误被静默忽略
+/// （如 `--where` 过滤掉所有记录、`--sort-by` 不起作用）
+#[derive(Debug, Default)]
+pub(crate) struct ColumnCheck {
+    /// 待检查的列名、所属的参数，以及是否可以是 `a.b`、`a[0]` 形式的嵌套路径
+    pending: Vec<(String, &'static str, bool)>,
+}
+
+impl ColumnCheck {
+    /// 按 [`lookup`] 查找的列，可以是嵌套路径
+    pub fn paths(mut self, option: &'static str, names: impl IntoIterator<Item = String>) -> Self {
+        self.pending
+            .extend(names.into_iter().map(|name| (name, option, true)));
+        self
+    }
+
+    /// 只作用于记录顶层字段的列
+    pub fn keys(mut self, option: &'static str, names: impl IntoIterator<Item = String>) -> Self {
+        self.pending
+            .extend(names.into_iter().map(|name| (name, option, false)));
+        self
+    }
+
+    /// 只检查第一次调用时的记录，之后直接返回
+    pub fn check(&mut self, record: &Value) -> Result<()> {
+        let missing = self.pending.iter().find(|(name, _, nested)| {
+            if *nested {
+                lookup(record, name).is_none()
+            } else {
+                record.get(name).is_none()
+            }
+        });
+        if let Some((name, option, _)) = missing {
+            return Err(anyhow!("Unknown column `{}` in {}", name, option));
+        }
+        self.pending.clear();
+        Ok(())
+    }
+}
+
+/// 解析列名路径，不符合 `key(.key|[n])*` 形式（如 `No.`、`a..b`）或下标不小于 `limit` 时
+/// 返回 `None`，按普通列名处理
+fn parse_path(key: &str, limit: usize) -> Option<Vec<Segment>> {
+    let mut segments = Vec::new();
+    for part in key.split('.') {
+        let (name, mut rest) = part.split_at(part.find('[').unwrap_or(part.len()));
+        if name.is_empty() {
+            return None;
+        }
+        segments.push(Segment::Key(name.to_string()));
+        while !rest.is_empty() {
+            let end = rest.find(']')?;
+            let index = rest.get(1..end)?.parse().ok().filter(|&i| i < limit)?;
+            segments.push(Segment::Index(index));
+            rest = &rest[end + 1..];
+            if !rest.is_empty() && !rest.starts_with('[') {
+                return None;
+            }
+        }
+    }
+    Some(segments)
+}
+
+/// 把值放到路径对应的位置，路径与已有的值冲突时返回 false
+fn insert(slot: &mut Value, path: &[Segment], value: Value) -> bool {
+    let Some((first, rest)) = path.split_first() else {
+        if !slot.is_null() {
+            return false;
+        }
+        *slot = value;
+        return true;
+    };
+
+    match first {
+        Segment::Key(key) => {
+            if slot.is_null() {
+                *slot = Value::Object(Map::new());
+            }
+            let Value::Object(map) = slot else {
+                return false;
+            };
+            insert(map.entry(key.clone()).or_insert(Value::Null), rest, value)
+        }
+        Segment::Index(i) => {
+            if slot.is_null() {
+                *slot = Value::Array(Vec::new());
+            }
+            let Value::Array(items) = slot else {
+                return false;
+            };
+            if items.len() <= *i {
+                items.resize(*i + 1, Value::Null);
+            }
+            insert(&mut items[*i], rest, value)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parse_paths() {
+        use Segment::{Index, Key};
+        assert_eq!(
+            parse_path("a.b[1][0].c", 10),
+            Some(vec![
+                Key("a".into()),
+                Key("b".into()),
+                Index(1),
+                Index(0),
+                Key("c".into())
+            ])
+        );
+        for key in ["No.", "a..b", ".a", "a[x]", "a[1]b", "a[1"] {
+            assert_eq!(parse_path(key, 10), None, "{}", key);
+        }
+        assert_eq!(parse_path("a[10]", 10), None);
+        assert_eq!(
+            parse_path("a[99999999999999]", usize::MAX).map(|p| p.len()),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn unflatten_records() {
+        let record = json!({"a.b": 1, "a.c": "x", "tags[1]": "y", "tags[0]": "z", "No.": 3});
+        assert_eq!(
+            unflatten(record).unwrap(),
+            json!({"a": {"b": 1, "c": "x"}, "tags": ["z", "y"], "No.": 3})
+        );
+        // 下标超过列数时按普通列名处理，不会分配巨大的数组
+        let record = json!({"a[18446744073709551614]": 1, "b[3]": 2});
+        assert_eq!(unflatten(record.clone()).unwrap(), record);
+        assert!(unflatten(json!({"a": 1, "a.b": 2})).is_err());
+    }
+
+    #[test]
+    fn lookup_paths() {
+        let record = json!({"a": {"b": [1, {"c": 2}]}, "x.y": 3});
+        assert_eq!(lookup(&record, "a.b[1].c"), Some(&json!(2)));
+        assert_eq!(lookup(&record, "x.y"), Some(&json!(3)));
+        assert_eq!(lookup(&record, "a.b[2]"), None);
+    }
+}
@@ -0,0 +1,578 @@
+use anyhow::{anyhow, Context, Result};
+use csv::{ByteRecord, Reader, ReaderBuilder, StringRecord};
+use serde_json::Value;
+use std::io::{BufRead, Read};
+
+use super::binary::read_document;
+use super::csv_infer::{FieldConverter, FieldInference, RecordConverter, TypeInference};
+use super::encoding::decode_reader;
+use super::filter::Filter;
+use super::nested::unflatten;
+use super::rejects::{Reject, Rejects};
+use super::schema::{Schema, Validator};
+use super::sheet::load_sheet;
+use super::transform::Projection;
+use super::xml::read_xml;
+use crate::opts::{ColumnType, CsvReadOpts, InputFormat, OnError, OutputFormat};
+use crate::utils::{get_reader, spool_stdin};
+
+/// 读取输入中的记录，逐条转换为 JSON 对象，经过 `--where` 过滤和列的选择、重命名后交给 `f` 处理，
+/// `input` 为 `-` 时读取标准输入
+///
+/// CSV 和工作表中 `address.city`、`tags[0]` 形式的列名会还原为嵌套的对象和数组（`--flat` 关闭）。
+/// 这两种输入返回经过列投影后的表头，没有记录时写出方可以据此输出表头。
+/// `force` 为 true 时覆盖已存在的 `--rejects` 文件
+pub(crate) fn read_records(
+    input: &str,
+    opts: &CsvReadOpts,
+    force: bool,
+    mut f: impl FnMut(Value) -> Result<()>,
+) -> Result<Option<Vec<String>>> {
+    let mut filter = opts.filter.as_deref().map(Filter::parse).transpose()?;
+    let mut projection = Projection::new(opts);
+    let format = input_format(input, opts);
+    let tabular = matches!(format, InputFormat::Csv | InputFormat::Excel);
+    let nest = should_nest(input, opts);
+    let f = |record| {
+        // 先过滤再做列投影，这样过滤条件可以引用被排除的列；最后再还原嵌套结构，
+        // 因此 `--where`、`--select` 等都使用 CSV 中的原始列名
+        if let Some(filter) = &mut filter {
+            if !filter.matches(&record)? {
+                return Ok(());
+            }
+        }
+        let record = projection.apply(record)?;
+        f(if nest { unflatten(record)? } else { record })
+    };
+    if opts.schema.is_some() && !tabular {
+        return Err(anyhow!(
+            "--schema is only supported for CSV and spreadsheet input"
+        ));
+    }
+    // 只有表格输入有可以单独拒绝的行，其他格式解析失败时整个文件无法读取
+    if (opts.on_error != OnError::Fail || opts.rejects.is_some()) && !tabular {
+        return Err(anyhow!(
+            "--on-error and --rejects are only supported for CSV and spreadsheet input"
+        ));
+    }
+    let header = match format {
+        InputFormat::Csv => read_csv(input, opts, force, f)?,
+        InputFormat::Excel => read_sheet(input, opts, force, f)?,
+        format => {
+            read_typed(input, format, opts, f)?;
+            return Ok(None);
+        }
+    };
+    if header.is_empty() {
+        return Ok(None);
+    }
+    // 用各列都为 null 的记录得到列投影后的列名
+    let template = header
+        .iter()
+        .map(|name| (name.to_string(), Value::Null))
+        .collect();
+    let projected = Projection::new(opts).apply(Value::Object(template))?;
+    Ok(projected
+        .as_object()
+        .map(|map| map.keys().cloned().collect()))
+}
+
+/// CSV 和工作表中 `address.city`、`tags[0]` 形式的列名是否还原为嵌套结构，`--flat` 时不还原
+pub(crate) fn should_nest(input: &str, opts: &CsvReadOpts) -> bool {
+    !opts.flat
+        && matches!(
+            input_format(input, opts),
+            InputFormat::Csv | InputFormat::Excel
+        )
+}
+
+/// 值都是文本、需要推断类型才能得到数字和布尔值的输入格式
+fn is_textual(format: InputFormat) -> bool {
+    matches!(
+        format,
+        InputFormat::Csv | InputFormat::Excel | InputFormat::Xml
+    )
+}
+
+/// 以 `format` 写出时读取 `input` 的参数：列式格式的每一列都需要确定的类型，因此总是推断
+/// CSV、XML 等文本输入的类型，与 `--infer` 的 JSON 输出一致；JSON 等输入保持原有的类型
+pub(crate) fn read_opts_for(input: &str, read: &CsvReadOpts, format: OutputFormat) -> CsvReadOpts {
+    CsvReadOpts {
+        infer: read.infer || (format.is_columnar() && is_textual(input_format(input, read))),
+        ..read.clone()
+    }
+}
+
+/// 合并多个来源的记录时缺少的列，与空单元格相同：推断类型时为 null，否则为空字符串
+pub(crate) fn missing_value(infer: bool) -> Value {
+    if infer {
+        Value::Null
+    } else {
+        Value::String(String::new())
+    }
+}
+
+/// 读取 XML、JSON 等非表格输入；`--infer` 和 `--types` 按字段名作用于记录顶层的字符串值
+fn read_typed(
+    input: &str,
+    format: InputFormat,
+    opts: &CsvReadOpts,
+    mut f: impl FnMut(Value) -> Result<()>,
+) -> Result<()> {
+    if !opts.infer && opts.types.is_empty() {
+        return read_untyped(input, format, opts, f);
+    }
+    // 推断类型需要先完整扫描一遍，标准输入先转存到临时文件
+    if opts.infer && input == "-" {
+        let spooled = spool_stdin()?;
+        return read_typed(&spooled.path().to_string_lossy(), format, opts, f);
+    }
+
+    let inferred = if opts.infer {
+        let mut inference = FieldInference::default();
+        read_untyped(input, format, opts, |record| {
+            inference.observe(&record);
+            Ok(())
+        })?;
+        Some(inference.finish())
+    } else {
+        None
+    };
+    let mut converter = FieldConverter::new(inferred, &opts.types)?;
+    read_untyped(input, format, opts, |record| f(converter.convert(record)?))
+}
+
+fn read_untyped(
+    input: &str,
+    format: InputFormat,
+    opts: &CsvReadOpts,
+    f: impl FnMut(Value) -> Result<()>,
+) -> Result<()> {
+    match format {
+        InputFormat::Xml => read_xml(input, opts.xml_row.as_deref(), f),
+        format => read_structured(input, format, f),
+    }
+}
+
+/// 输入格式由 `--input-format` 指定，否则按扩展名判断
+pub(crate) fn input_format(input: &str, opts: &CsvReadOpts) -> InputFormat {
+    opts.input_format
+        .unwrap_or_else(|| InputFormat::from_path(input))
+}
+
+/// CSV 文件或工作表的一次完整扫描；推断类型、schema 检查和转换各扫描一次
+trait Rows {
+    /// 有表头时为表头，否则为第一行数据（仅用于确定列数）
+    fn first(&mut self) -> Result<StringRecord>;
+
+    /// 逐行读取记录；`--on-error` 不为 fail 时，无法解析的行作为 `Err` 交给 `f`，而不是中止读取
+    fn for_each_row(
+        &mut self,
+        opts: &CsvReadOpts,
+        f: &mut dyn FnMut(Result<&StringRecord, Reject>) -> Result<()>,
+    ) -> Result<()>;
+}
+
+/// 读取 CSV 文件，把每条记录按列类型转换为 JSON 对象，返回表头
+fn read_csv(
+    input: &str,
+    opts: &CsvReadOpts,
+    force: bool,
+    f: impl FnMut(Value) -> Result<()>,
+) -> Result<StringRecord> {
+    // 推断类型和 schema 检查需要先完整扫描一遍文件，标准输入只能读一次，先转存到临时文件
+    if (opts.infer || opts.schema.is_some()) && input == "-" {
+        let spooled = spool_stdin()?;
+        return read_csv(&spooled.path().to_string_lossy(), opts, force, f);
+    }
+    read_table(opts, force, || Ok(Box::new(open_reader(input, opts)?)), f)
+}
+
+/// 读取 Excel/ODS 工作表，每行按 CSV 记录处理，返回表头
+fn read_sheet(
+    input: &str,
+    opts: &CsvReadOpts,
+    force: bool,
+    f: impl FnMut(Value) -> Result<()>,
+) -> Result<StringRecord> {
+    let records = load_sheet(input, opts.sheet.as_deref())?;
+    read_table(
+        opts,
+        force,
+        || {
+            Ok(Box::new(SheetRows {
+                records: &records,
+                header: opts.header,
+            }))
+        },
+        f,
+    )
+}
+
+fn read_table<'a>(
+    opts: &CsvReadOpts,
+    force: bool,
+    open: impl Fn() -> Result<Box<dyn Rows + 'a>>,
+    mut f: impl FnMut(Value) -> Result<()>,
+) -> Result<StringRecord> {
+    let inferred = if opts.infer {
+        Some(infer_types(open()?, opts)?)
+    } else {
+        None
+    };
+
+    let mut rows = open()?;
+    let header = build_header(&rows.first()?, opts.header, opts.columns.as_deref())?;
+
+    // schema 中声明的类型优先于推断结果，`--types` 又优先于 schema
+    let mut types = Vec::new();
+    if let Some(path) = &opts.schema {
+        let schema = Schema::load(path)?;
+        let validator = schema.bind(&header)?;
+        validate(open()?, opts, &validator)?;
+        types = validator.types();
+    }
+    types.extend(opts.types.iter().cloned());
+
+    let mut rejects = Rejects::create(opts, &header, force)?;
+    let converter = RecordConverter::new(header.clone(), inferred, &types)?;
+    rows.for_each_row(opts, &mut |row| {
+        let record = match row {
+            Ok(record) => record,
+            Err(reject) => return rejects.add(reject),
+        };
+        match converter.convert(record) {
+            Ok(value) => f(value),
+            Err(e) => rejects.add(Reject::new(record.as_byte_record(), e)),
+        }
+    })?;
+    rejects.finish()?;
+    Ok(header)
+}
+
+fn open_reader(input: &str, opts: &CsvReadOpts) -> Result<Reader<impl Read>> {
+    Ok(ReaderBuilder::new()
+        .delimiter(opts.delimiter)
+        .has_headers(opts.header)
+        // 宽松模式下自行检查列数，以便把列数不符的行交给 `--on-error` 处理
+        .flexible(opts.on_error != OnError::Fail)
+        .from_reader(decode_reader(input, opts.encoding)?))
+}
+
+impl<R: Read> Rows for Reader<R> {
+    fn first(&mut self) -> Result<StringRecord> {
+        Ok(self.headers()?.clone())
+    }
+
+    /// 宽松模式下列数与表头不符或不是合法 UTF-8 的行被拒绝
+    fn for_each_row(
+        &mut self,
+        opts: &CsvReadOpts,
+        f: &mut dyn FnMut(Result<&StringRecord, Reject>) -> Result<()>,
+    ) -> Result<()> {
+        if opts.on_error == OnError::Fail {
+            let mut record = StringRecord::new();
+            while self
+                .read_record(&mut record)
+                .context("Malformed CSV row, use --on-error skip or collect to continue past it")?
+            {
+                f(Ok(&record))?;
+            }
+            return Ok(());
+        }
+
+        let width = self.headers()?.len();
+        let mut raw = ByteRecord::new();
+        while self.read_byte_record(&mut raw)? {
+            if raw.len() != width {
+                let error = format!("expected {} fields, found {}", width, raw.len());
+                f(Err(Reject::new(&raw, error)))?;
+                continue;
+            }
+            // 复用同一个缓冲区，避免逐行分配
+            raw = match StringRecord::from_byte_record(std::mem::take(&mut raw)) {
+                Ok(record) => {
+                    f(Ok(&record))?;
+                    record.into_byte_record()
+                }
+                Err(e) => {
+                    let error = e.utf8_error().to_string();
+                    let raw = e.into_byte_record();
+                    f(Err(Reject::new(&raw, error)))?;
+                    raw
+                }
+            };
+        }
+        Ok(())
+    }
+}
+
+/// 已读入内存的工作表，每行都是合法的记录
+struct SheetRows<'a> {
+    records: &'a [StringRecord],
+    header: bool,
+}
+
+impl Rows for SheetRows<'_> {
+    fn first(&mut self) -> Result<StringRecord> {
+        Ok(self.records.first().cloned().unwrap_or_default())
+    }
+
+    fn for_each_row(
+        &mut self,
+        _opts: &CsvReadOpts,
+        f: &mut dyn FnMut(Result<&StringRecord, Reject>) -> Result<()>,
+    ) -> Result<()> {
+        let skip = usize::from(self.header);
+        for record in self.records.iter().skip(skip) {
+            f(Ok(record))?;
+        }
+        Ok(())
+    }
+}
+
+fn infer_types(mut rows: Box<dyn Rows + '_>, opts: &CsvReadOpts) -> Result<Vec<ColumnType>> {
+    let mut inference = TypeInference::new(rows.first()?.len());
+    // 被拒绝的行不参与推断，由转换时统一报告
+    rows.for_each_row(opts, &mut |row| {
+        if let Ok(record) = row {
+            inference.observe(record);
+        }
+        Ok(())
+    })?;
+    Ok(inference.finish())
+}
+
+/// 转换前完整检查一遍输入，有任何违反 schema 的行时不输出记录
+fn validate(mut rows: Box<dyn Rows + '_>, opts: &CsvReadOpts, validator: &Validator) -> Result<()> {
+    const MAX_REPORTED: usize = 20;
+
+    let mut count = 0;
+    rows.for_each_row(opts, &mut |row| {
+        let Ok(record) = row else {
+            return Ok(());
+        };
+        for violation in validator.validate(record) {
+            if count < MAX_REPORTED {
+                eprintln!("{}", violation);
+            }
+            count += 1;
+        }
+        Ok(())
+    })?;
+
+    if count > MAX_REPORTED {
+        eprintln!("... and {} more", count - MAX_REPORTED);
+    }
+    if count > 0 {
+        return Err(anyhow!("{} schema violation(s) found", count));
+    }
+    Ok(())
+}
+
+/// 确定输出使用的列名：优先使用 `--columns`，其次是文件表头，最后生成 col1, col2, ...
+fn build_header(
+    first: &StringRecord,
+    has_header: bool,
+    columns: Option<&[String]>,
+) -> Result<StringRecord> {
+    match columns {
+        Some(columns) if columns.len() != first.len() => Err(anyhow!(
+            "Expected {} column names, got {}",
+            first.len(),
+            columns.len()
+        )),
+        Some(columns) => Ok(columns.iter().collect()),
+        None if has_header => Ok(first.clone()),
+        None => Ok((1..=first.len()).map(|i| format!("col{}", i)).collect()),
+    }
+}
+
+/// 读取 JSON/YAML/TOML/MessagePack/CBOR/BSON 文件中的记录数组
+fn read_structured(
+    path: &str,
+    format: InputFormat,
+    mut f: impl FnMut(Value) -> Result<()>,
+) -> Result<()> {
+    let doc: Value = match format {
+        InputFormat::Ndjson => {
+            // NDJSON 可以逐行读取，无需把整个文件载入内存
+            for (i, line) in get_reader(path)?.lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let record =
+                    serde_json::from_str(&line).map_err(|e| anyhow!("line {}: {}", i + 1, e))?;
+                f(expect_object(record, i)?)?;
+            }
+            return Ok(());
+        }
+        format => load_document(path, format)?,
+    };
+
+    for (i, record) in into_records(doc)?.into_iter().enumerate() {
+        f(expect_object(record, i)?)?;
+    }
+    Ok(())
+}
+
+/// 整体读取 JSON/YAML/TOML/MessagePack/CBOR/BSON 文档
+pub(crate) fn load_document(path: &str, format: InputFormat) -> Result<Value> {
+    let doc = match format {
+        InputFormat::Json => serde_json::from_reader(get_reader(path)?)?,
+        InputFormat::Msgpack | InputFormat::Cbor | InputFormat::Bson => {
+            read_document(path, format)?
+        }
+        InputFormat::Yaml => serde_yaml::from_reader(get_reader(path)?)?,
+        InputFormat::Toml => {
+            let mut content = String::new();
+            get_reader(path)?.read_to_string(&mut content)?;
+            let mut doc = toml::from_str(&content)?;
+            toml_datetimes_to_strings(&mut doc);
+            doc
+        }
+        InputFormat::Csv | InputFormat::Excel | InputFormat::Xml | InputFormat::Ndjson => {
+            unreachable!("CSV, spreadsheet, XML and NDJSON input are read record by record")
+        }
+    };
+    Ok(doc)
+}
+
+/// TOML 的日期时间反序列化为只含一个私有字段的对象，还原为原文的字符串
+fn toml_datetimes_to_strings(value: &mut Value) {
+    const DATETIME_FIELD: &str = "$__toml_private_datetime";
+
+    match value {
+        Value::Object(map) => match map.get(DATETIME_FIELD) {
+            Some(Value::String(s)) if map.len() == 1 => *value = Value::String(s.clone()),
+            _ => map.values_mut().for_each(toml_datetimes_to_strings),
+        },
+        Value::Array(items) => items.iter_mut().for_each(toml_datetimes_to_strings),
+        _ => {}
+    }
+}
+
+/// 顶层为数组时直接使用；顶层为只含一个数组的表（如 `[[players]]`）时使用该数组
+pub(crate) fn into_records(doc: Value) -> Result<Vec<Value>> {
+    match doc {
+        Value::Array(items) => Ok(items),
+        Value::Object(map) if map.len() == 1 => match map.into_iter().next() {
+            Some((_, Value::Array(items))) => Ok(items),
+            _ => Err(anyhow!("Expected an array of records")),
+        },
+        _ => Err(anyhow!("Expected an array of records")),
+    }
+}
+
+pub(crate) fn expect_object(record: Value, index: usize) -> Result<Value> {
+    if record.is_object() {
+        Ok(record)
+    } else {
+        Err(anyhow!("Record {} is not an object", index + 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use serde_json::json;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn read_all(input: &str, args: &[&str]) -> Result<Vec<Value>> {
+        let opts = CsvReadOpts::parse_from(["csv"].iter().chain(args));
+        let mut records = Vec::new();
+        read_records(input, &opts, false, |record| {
+            records.push(record);
+            Ok(())
+        })?;
+        Ok(records)
+    }
+
+    #[test]
+    fn collects_rejected_rows() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("in.csv");
+        fs::write(
+            &input,
+            b"name,age\nAnn,30\nBob,31,extra\nRen\xe9e,40\nCid,old\nDan,20\n",
+        )
+        .unwrap();
+        let rejects = dir.path().join("rejects.csv");
+        let input = input.to_str().unwrap();
+        let rejects = rejects.to_str().unwrap();
+        let args = [
+            "--types",
+            "age:int",
+            "--on-error",
+            "collect",
+            "--rejects",
+            rejects,
+        ];
+
+        let records = read_all(input, &args).unwrap();
+        assert_eq!(
+            records,
+            [
+                json!({"name": "Ann", "age": 30}),
+                json!({"name": "Dan", "age": 20})
+            ]
+        );
+        let report = fs::read_to_string(rejects).unwrap();
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines.len(), 4, "{}", report);
+        assert_eq!(lines[0], "line,error,name,age");
+        assert!(lines[1].starts_with("3,") && lines[1].ends_with(",Bob,31,extra"));
+        assert!(lines[2].starts_with("4,") && lines[2].contains("UTF-8"));
+        assert!(lines[3].starts_with("5,") && lines[3].ends_with(",Cid,old"));
+
+        // 已存在的 rejects 文件不会被覆盖
+        assert!(read_all(input, &args).is_err());
+        // skip 只丢弃，fail 在第一个被拒绝的行报错
+        assert_eq!(
+            read_all(input, &["--types", "age:int", "--on-error", "skip"])
+                .unwrap()
+                .len(),
+            2
+        );
+        assert!(read_all(input, &["--types", "age:int"]).is_err());
+    }
+
+    #[test]
+    fn rejects_need_tabular_input() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("in.json");
+        fs::write(&input, r#"[{"a": 1}]"#).unwrap();
+        let input = input.to_str().unwrap();
+        assert!(read_all(input, &[]).is_ok());
+        assert!(read_all(input, &["--on-error", "skip"]).is_err());
+        assert!(read_all(input, &["--on-error", "collect", "--rejects", "r.csv"]).is_err());
+    }
+
+    #[test]
+    fn infers_and_types_xml_fields() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("in.xml");
+        fs::write(
+            &input,
+            "<r><p><n>7</n><s>1.5</s><ok>true</ok><id>007</id></p><p><n/><s>2</s><ok>false</ok><id>8</id></p></r>",
+        )
+        .unwrap();
+        let input = input.to_str().unwrap();
+        assert_eq!(
+            read_all(input, &["--infer"]).unwrap(),
+            [
+                json!({"n": 7, "s": 1.5, "ok": true, "id": "007"}),
+                json!({"n": null, "s": 2.0, "ok": false, "id": "8"}),
+            ]
+        );
+        assert_eq!(
+            read_all(input, &["--types", "s:float"]).unwrap()[1],
+            json!({"n": "", "s": 2.0, "ok": "false", "id": "8"})
+        );
+        assert!(read_all(input, &["--infer", "--types", "x:int"]).is_err());
+        assert!(read_all(input, &["--types", "x:int"]).is_err());
+    }
+}
@@ -0,0 +1,108 @@
+use anyhow::{anyhow, Result};
+use csv::{ByteRecord, StringRecord, Writer, WriterBuilder};
+use std::io::Write;
+
+use crate::opts::{CsvReadOpts, OnError};
+use crate::utils::{create_output, OutputFile};
+
+/// 无法解析或转换的一行
+#[derive(Debug)]
+pub(crate) struct Reject {
+    pub line: u64,
+    pub error: String,
+    pub fields: Vec<String>,
+}
+
+impl Reject {
+    pub fn new(record: &ByteRecord, error: impl ToString) -> Self {
+        Self {
+            line: record.position().map(|p| p.line()).unwrap_or_default(),
+            error: error.to_string(),
+            // 非法的 UTF-8 字节替换为 U+FFFD，保证 rejects 文件本身可读
+            fields: record
+                .iter()
+                .map(|field| String::from_utf8_lossy(field).into_owned())
+                .collect(),
+        }
+    }
+}
+
+/// 按 `--on-error` 处理被拒绝的行：fail 立即报错，skip 丢弃，collect 写入 `--rejects` 文件
+pub(crate) struct Rejects {
+    mode: OnError,
+    writer: Option<RejectsFile>,
+    count: usize,
+}
+
+/// `--rejects` 文件，写完后才出现在目标路径
+struct RejectsFile {
+    path: String,
+    writer: Writer<Box<dyn Write>>,
+    file: OutputFile,
+}
+
+impl Rejects {
+    /// rejects 文件的表头为 `line,error` 加上原始列名，每行在原始字段前加上行号和错误；
+    /// 文件已存在时只有 `force` 为 true 才覆盖
+    pub fn create(opts: &CsvReadOpts, header: &StringRecord, force: bool) -> Result<Self> {
+        let writer = match (opts.on_error, &opts.rejects) {
+            (OnError::Collect, Some(path)) => {
+                let (out, file) = create_output(path, force)?;
+                let mut writer = WriterBuilder::new()
+                    .delimiter(opts.delimiter)
+                    .flexible(true)
+                    .from_writer(out);
+                writer.write_record(["line", "error"].into_iter().chain(header.iter()))?;
+                Some(RejectsFile {
+                    path: path.clone(),
+                    writer,
+                    file,
+                })
+            }
+            (OnError::Collect, None) => {
+                return Err(anyhow!("--on-error collect requires --rejects <file>"))
+            }
+            (_, Some(_)) => return Err(anyhow!("--rejects requires --on-error collect")),
+            (_, None) => None,
+        };
+        Ok(Self {
+            mode: opts.on_error,
+            writer,
+            count: 0,
+        })
+    }
+
+    pub fn add(&mut self, reject: Reject) -> Result<()> {
+        if self.mode == OnError::Fail {
+            return Err(anyhow!("line {}: {}", reject.line, reject.error));
+        }
+        if let Some(rejects) = &mut self.writer {
+            let line = reject.line.to_string();
+            rejects.writer.write_record(
+                [line.as_str(), reject.error.as_str()]
+                    .into_iter()
+                    .chain(reject.fields.iter().map(String::as_str)),
+            )?;
+        }
+        self.count += 1;
+        Ok(())
+    }
+
+    /// 写完 rejects 文件，并在标准错误中报告被拒绝的行数
+    pub fn finish(self) -> Result<()> {
+        match self.writer {
+            Some(RejectsFile { path, writer, file }) => {
+                let mut out = writer.into_inner().map_err(|e| e.into_error())?;
+                out.flush()?;
+                drop(out);
+                file.commit()?;
+                if self.count > 0 {
+                    eprintln!("Wrote {} rejected row(s) to {}", self.count, path);
+                }
+            }
+            None if self.count > 0 => eprintln!("Skipped {} malformed row(s)", self.count),
+            None => {}
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,408 @@
+use anyhow::{anyhow, Result};
+use csv::StringRecord;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::fmt;
+
+use super::csv_infer::parse_typed;
+use crate::opts::ColumnType;
+use crate::utils::get_reader;
+
+/// `--schema` 文件中单列的规则
+///
+/// ```yaml
+/// columns:
+///   Name: { type: string, required: true, pattern: "^[A-Z]" }
+///   Position: { enum: [Goalkeeper, Defender, Midfielder, Forward] }
+///   Kit Number: { type: int, min: 1, max: 99 }
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ColumnSpec {
+    #[serde(rename = "type")]
+    ty: Option<String>,
+    #[serde(default)]
+    required: bool,
+    pattern: Option<String>,
+    #[serde(rename = "enum", default)]
+    allowed: Vec<Value>,
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SchemaFile {
+    columns: Map<String, Value>,
+}
+
+#[derive(Debug)]
+struct Rule {
+    name: String,
+    ty: Option<ColumnType>,
+    required: bool,
+    pattern: Option<Regex>,
+    allowed: Vec<Value>,
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+/// 声明的列规则，支持 rcli 自己的格式和 JSON Schema 的子集
+#[derive(Debug)]
+pub(crate) struct Schema {
+    rules: Vec<Rule>,
+}
+
+/// 一处违反 schema 的位置，行号与列号均从 1 开始
+#[derive(Debug)]
+pub(crate) struct Violation {
+    pub line: u64,
+    pub column: usize,
+    pub name: String,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {} (`{}`): {}",
+            self.line, self.column, self.name, self.message
+        )
+    }
+}
+
+impl Schema {
+    /// 读取 YAML 或 JSON 格式的 schema 文件；含 `properties` 时按 JSON Schema 解析
+    pub fn load(path: &str) -> Result<Self> {
+        let doc: Value = serde_yaml::from_reader(get_reader(path)?)
+            .map_err(|e| anyhow!("Invalid schema `{}`: {}", path, e))?;
+        let schema = if doc.get("properties").is_some() {
+            Self::from_json_schema(doc)
+        } else {
+            Self::from_native(doc)
+        };
+        schema.map_err(|e| anyhow!("Invalid schema `{}`: {}", path, e))
+    }
+
+    fn from_native(doc: Value) -> Result<Self> {
+        let file: SchemaFile = serde_json::from_value(doc)?;
+        let rules = file
+            .columns
+            .into_iter()
+            .map(|(name, spec)| {
+                // `Name: {}` 或 `Name:` 表示只声明列，不做其他检查
+                let spec: ColumnSpec = match spec {
+                    Value::Null => ColumnSpec::default(),
+                    spec => serde_json::from_value(spec)
+                        .map_err(|e| anyhow!("column `{}`: {}", name, e))?,
+                };
+                Rule::new(name, spec)
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { rules })
+    }
+
+    /// 支持 `properties`、`required` 以及属性中的 `type`、`format: date`、`pattern`、
+    /// `enum`、`minimum`、`maximum`，其他关键字被忽略
+    fn from_json_schema(doc: Value) -> Result<Self> {
+        let required: Vec<&str> = doc
+            .get("required")
+            .and_then(Value::as_array)
+            .map(|names| names.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+        let properties = doc
+            .get("properties")
+            .and_then(Value::as_object)
+            .ok_or_else(|| anyhow!("`properties` must be an object"))?;
+
+        let rules = properties
+            .iter()
+            .map(|(name, prop)| {
+                let ty = match (
+                    prop.get("type").and_then(Value::as_str),
+                    prop.get("format").and_then(Value::as_str),
+                ) {
+                    (Some("string") | None, Some("date")) => Some("date"),
+                    (Some("string"), _) => Some("string"),
+                    (Some("integer"), _) => Some("int"),
+                    (Some("number"), _) => Some("float"),
+                    (Some("boolean"), _) => Some("bool"),
+                    (Some(other), _) => {
+                        return Err(anyhow!("property `{}`: unsupported type `{}`", name, other))
+                    }
+                    (None, _) => None,
+                };
+                let spec = ColumnSpec {
+                    ty: ty.map(str::to_string),
+                    required: required.contains(&name.as_str()),
+                    pattern: prop
+                        .get("pattern")
+                        .and_then(Value::as_str)
+                        .map(str::to_string),
+                    allowed: prop
+                        .get("enum")
+                        .and_then(Value::as_array)
+                        .cloned()
+                        .unwrap_or_default(),
+                    min: prop.get("minimum").and_then(Value::as_f64),
+                    max: prop.get("maximum").and_then(Value::as_f64),
+                };
+                Rule::new(name.clone(), spec)
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { rules })
+    }
+
+    /// 按表头确定每条规则对应的列；必填列不存在时报错，可选列不存在时忽略
+    pub fn bind(&self, header: &StringRecord) -> Result<Validator<'_>> {
+        let mut rules = Vec::new();
+        for rule in &self.rules {
+            match header.iter().position(|h| h == rule.name) {
+                Some(idx) => rules.push((idx, rule)),
+                None if rule.required => {
+                    return Err(anyhow!(
+                        "Required column `{}` in --schema is not in the CSV header",
+                        rule.name
+                    ))
+                }
+                None => {}
+            }
+        }
+        // 同一行的问题按列的顺序报告
+        rules.sort_by_key(|(idx, _)| *idx);
+        Ok(Validator { rules })
+    }
+}
+
+impl Rule {
+    fn new(name: String, spec: ColumnSpec) -> Result<Self> {
+        let ty = spec
+            .ty
+            .as_deref()
+            .map(str::parse::<ColumnType>)
+            .transpose()
+            .map_err(|e| anyhow!("column `{}`: {}", name, e))?;
+        if (spec.min.is_some() || spec.max.is_some())
+            && !matches!(ty, Some(ColumnType::Int | ColumnType::Float))
+        {
+            return Err(anyhow!(
+                "column `{}`: min/max require type int or float",
+                name
+            ));
+        }
+        let pattern = spec
+            .pattern
+            .as_deref()
+            .map(Regex::new)
+            .transpose()
+            .map_err(|e| anyhow!("column `{}`: {}", name, e))?;
+
+        Ok(Self {
+            name,
+            ty,
+            required: spec.required,
+            pattern,
+            allowed: spec.allowed,
+            min: spec.min,
+            max: spec.max,
+        })
+    }
+
+    fn check(&self, value: &str) -> Result<(), String> {
+        if value.is_empty() {
+            return if self.required {
+                Err("value is required".to_string())
+            } else {
+                Ok(())
+            };
+        }
+
+        let typed =
+            parse_typed(self.ty.unwrap_or(ColumnType::String), value).map_err(|e| e.to_string())?;
+        if let Some(pattern) = &self.pattern {
+            if !pattern.is_match(value) {
+                return Err(format!("`{}` does not match /{}/", value, pattern));
+            }
+        }
+        if !self.allowed.is_empty()
+            && !self
+                .allowed
+                .iter()
+                .any(|a| same_value(a, &typed) || display_value(a) == value)
+        {
+            let allowed: Vec<String> = self.allowed.iter().map(display_value).collect();
+            return Err(format!("`{}` is not one of {}", value, allowed.join(", ")));
+        }
+        if let Some(n) = typed.as_f64() {
+            if let Some(min) = self.min.filter(|min| n < *min) {
+                return Err(format!("{} is less than the minimum {}", value, min));
+            }
+            if let Some(max) = self.max.filter(|max| n > *max) {
+                return Err(format!("{} is greater than the maximum {}", value, max));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 绑定到具体表头的 schema，逐行检查
+#[derive(Debug)]
+pub(crate) struct Validator<'a> {
+    rules: Vec<(usize, &'a Rule)>,
+}
+
+impl Validator<'_> {
+    /// schema 中声明了类型的列，转换时按该类型输出
+    pub fn types(&self) -> Vec<(String, ColumnType)> {
+        self.rules
+            .iter()
+            .filter_map(|(_, rule)| rule.ty.map(|ty| (rule.name.clone(), ty)))
+            .collect()
+    }
+
+    pub fn validate(&self, record: &StringRecord) -> Vec<Violation> {
+        let line = record.position().map(|p| p.line()).unwrap_or_default();
+        self.rules
+            .iter()
+            .filter_map(|(idx, rule)| {
+                let message = rule.check(record.get(*idx).unwrap_or_default()).err()?;
+                Some(Violation {
+                    line,
+                    column: idx + 1,
+                    name: rule.name.clone(),
+                    message,
+                })
+            })
+            .collect()
+    }
+}
+
+/// 数字按数值比较，`1` 与 `1.0` 视为相同
+fn same_value(a: &Value, b: &Value) -> bool {
+    match (a.as_f64(), b.as_f64()) {
+        (Some(x), Some(y)) => x == y,
+        _ => a == b,
+    }
+}
+
+fn display_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn load(content: &str) -> Result<Schema> {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        Schema::load(&file.path().to_string_lossy())
+    }
+
+    /// 每条违反规则的位置写为 `列号:消息`
+    fn check(schema: &Schema, header: &[&str], row: &[&str]) -> Vec<String> {
+        let validator = schema.bind(&StringRecord::from(header.to_vec())).unwrap();
+        validator
+            .validate(&StringRecord::from(row.to_vec()))
+            .iter()
+            .map(|v| format!("{}:{}", v.column, v.message))
+            .collect()
+    }
+
+    const NATIVE: &str = r#"
+columns:
+  Name: { type: string, required: true, pattern: "^[A-Z]" }
+  Position: { enum: [Goalkeeper, Defender] }
+  Kit: { type: int, min: 1, max: 99 }
+  Note:
+"#;
+
+    #[test]
+    fn validates_native_schema() {
+        let schema = load(NATIVE).unwrap();
+        let header = ["Kit", "Name", "Position", "Note"];
+        assert!(check(&schema, &header, &["7", "Alice", "Defender", "x"]).is_empty());
+        // 非必填列可以为空；同一行的问题按列的顺序报告
+        assert!(check(&schema, &header, &["", "Bob", "", ""]).is_empty());
+        assert_eq!(
+            check(&schema, &header, &["100", "", "Forward", ""]),
+            [
+                "1:100 is greater than the maximum 99",
+                "2:value is required",
+                "3:`Forward` is not one of Goalkeeper, Defender",
+            ]
+        );
+        assert_eq!(
+            check(&schema, &header, &["x", "bob", "Defender", ""]),
+            ["1:`x` is not an integer", "2:`bob` does not match /^[A-Z]/"]
+        );
+        let types = schema
+            .bind(&StringRecord::from(header.to_vec()))
+            .unwrap()
+            .types();
+        assert_eq!(
+            types,
+            [
+                ("Kit".to_string(), ColumnType::Int),
+                ("Name".to_string(), ColumnType::String),
+            ]
+        );
+    }
+
+    #[test]
+    fn binds_to_header() {
+        let schema = load(NATIVE).unwrap();
+        // 可选列不存在时忽略，必填列不存在时报错
+        assert!(check(&schema, &["Name"], &["Alice"]).is_empty());
+        let err = schema.bind(&StringRecord::from(vec!["Kit"])).unwrap_err();
+        assert!(err.to_string().contains("Required column `Name`"));
+    }
+
+    #[test]
+    fn validates_json_schema() {
+        let schema = load(
+            r#"{
+              "type": "object",
+              "required": ["id"],
+              "properties": {
+                "id": { "type": "integer", "minimum": 1 },
+                "born": { "type": "string", "format": "date" },
+                "level": { "type": "number", "enum": [1, 2] },
+                "active": { "type": "boolean" }
+              }
+            }"#,
+        )
+        .unwrap();
+        let header = ["id", "born", "level", "active"];
+        assert!(check(&schema, &header, &["3", "2001-02-03", "2", "yes"]).is_empty());
+        // 数字列的枚举按数值比较
+        assert!(check(&schema, &header, &["3", "", "2.0", ""]).is_empty());
+        assert_eq!(
+            check(&schema, &header, &["0", "soon", "3", "maybe"]),
+            [
+                "1:0 is less than the minimum 1",
+                "2:`soon` is not a recognized date",
+                "3:`3` is not one of 1, 2",
+                "4:`maybe` is not a boolean",
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_schemas() {
+        let err = |content: &str| load(content).unwrap_err().to_string();
+        assert!(err("columns:\n  A: { type: text }\n").contains("column `A`"));
+        assert!(err("columns:\n  A: { min: 1 }\n").contains("min/max require type int or float"));
+        assert!(err("columns:\n  A: { pattern: \"(\" }\n").contains("column `A`"));
+        assert!(err("columns:\n  A: { unknown: 1 }\n").contains("unknown"));
+        assert!(err("rows: {}\n").contains("Invalid schema"));
+        assert!(err(r#"{"properties": {"a": {"type": "array"}}}"#).contains("unsupported type"));
+    }
+}
@@ -0,0 +1,83 @@
+use anyhow::{anyhow, Result};
+use calamine::{open_workbook_auto, open_workbook_auto_from_rs, Data, Range, Reader, Sheets};
+use chrono::NaiveTime;
+use csv::{Position, StringRecord};
+use std::io::{Cursor, Read, Seek};
+
+use crate::utils::get_reader;
+
+/// 读取 Excel/ODS 工作表中的所有行，每行转为与 CSV 记录相同的字符串记录
+///
+/// `sheet` 为工作表名或从 1 开始的序号，默认读取第一个工作表。记录的行号为工作表中的行号
+pub(crate) fn load_sheet(input: &str, sheet: Option<&str>) -> Result<Vec<StringRecord>> {
+    let range = if input == "-" {
+        // 工作簿需要随机访问，标准输入先整个读入内存
+        let mut content = Vec::new();
+        get_reader(input)?.read_to_end(&mut content)?;
+        worksheet(open_workbook_auto_from_rs(Cursor::new(content))?, sheet)?
+    } else {
+        worksheet(open_workbook_auto(input)?, sheet)?
+    };
+
+    let first_row = range.start().map_or(0, |(row, _)| row as u64);
+    let records = range
+        .rows()
+        .enumerate()
+        .map(|(i, row)| {
+            let mut record: StringRecord = row.iter().map(cell_to_string).collect();
+            let mut position = Position::new();
+            position.set_line(first_row + i as u64 + 1);
+            record.set_position(Some(position));
+            record
+        })
+        .collect();
+    Ok(records)
+}
+
+fn worksheet<RS: Read + Seek>(
+    mut workbook: Sheets<RS>,
+    sheet: Option<&str>,
+) -> Result<Range<Data>> {
+    let names = workbook.sheet_names();
+    let name = match sheet {
+        None => names.first(),
+        // 优先按名称匹配，名称本身是数字的工作表也能选中
+        Some(sheet) => names.iter().find(|name| *name == sheet).or_else(|| {
+            sheet
+                .parse::<usize>()
+                .ok()
+                .and_then(|n| n.checked_sub(1))
+                .and_then(|n| names.get(n))
+        }),
+    }
+    .cloned()
+    .ok_or_else(|| {
+        anyhow!(
+            "Sheet `{}` not found, available sheets: {}",
+            sheet.unwrap_or("1"),
+            names.join(", ")
+        )
+    })?;
+    Ok(workbook.worksheet_range(&name)?)
+}
+
+/// 日期输出为 ISO 8601，整数值的浮点数（Excel 中的数字都是浮点数）去掉小数部分
+fn cell_to_string(cell: &Data) -> String {
+    match cell {
+        Data::Empty => String::new(),
+        Data::String(s) | Data::DateTimeIso(s) | Data::DurationIso(s) => s.clone(),
+        Data::Int(i) => i.to_string(),
+        Data::Float(f) if f.fract() == 0.0 && f.abs() < 1e15 => (*f as i64).to_string(),
+        Data::Float(f) => f.to_string(),
+        Data::Bool(b) => b.to_string(),
+        Data::DateTime(dt) if dt.is_duration() => dt.to_string(),
+        Data::DateTime(dt) => match dt.as_datetime() {
+            // 只有时间的单元格序列值小于 1
+            Some(t) if dt.as_f64() < 1.0 => t.format("%H:%M:%S").to_string(),
+            Some(t) if t.time() == NaiveTime::MIN => t.format("%Y-%m-%d").to_string(),
+            Some(t) => t.format("%Y-%m-%dT%H:%M:%S").to_string(),
+            None => dt.to_string(),
+        },
+        Data::Error(e) => e.to_string(),
+    }
+}
@@ -0,0 +1,534 @@
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use std::{
+    cmp::Ordering,
+    collections::BinaryHeap,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Lines, Write},
+    mem::size_of,
+};
+use tempfile::{NamedTempFile, TempPath};
+
+use super::nested::{lookup, unflatten, ColumnCheck};
+use super::reader::{read_records, should_nest};
+use crate::opts::{CsvReadOpts, CsvSortOpts, SortKey, SortMode};
+
+const MIB: usize = 1024 * 1024;
+/// 一轮最多同时归并的 run 数，更多时分多轮归并，限制同时打开的文件数
+const MERGE_FAN_IN: usize = 16;
+/// 每次堆分配在分配器中的额外开销
+const ALLOC_OVERHEAD: usize = 16;
+/// 保持字段顺序的对象中每个字段除键和值以外的开销：哈希、索引表以及容量余量
+const MAP_ENTRY_OVERHEAD: usize = 48;
+
+/// 与 [`read_records`] 相同（包括返回的表头），但按 `--sort-by` 排序、按 `--dedup-by` 去重后再交给 `f` 处理
+///
+/// 排序和去重使用 CSV 中的原始列名（经过 `--rename` 后的名字），之后再还原嵌套结构。
+/// 去重的列都按文本排在 `--sort-by` 的最前面时，排序后相同的键相邻，只需与前一行比较；
+/// 否则先按去重的列外部排序、保留每组的第一行，再按 `--sort-by` 排序，内存占用同样有上限
+pub(crate) fn read_sorted(
+    input: &str,
+    read: &CsvReadOpts,
+    sort: &CsvSortOpts,
+    force: bool,
+    mut f: impl FnMut(Value) -> Result<()>,
+) -> Result<Option<Vec<String>>> {
+    if sort.sort_by.is_empty() && sort.dedup_by.is_empty() {
+        return read_records(input, read, force, f);
+    }
+    let budget = match sort.sort_memory.checked_mul(MIB) {
+        Some(0) => return Err(anyhow!("--sort-memory must be greater than 0")),
+        Some(budget) => budget,
+        None => {
+            return Err(anyhow!(
+                "--sort-memory must be at most {} (MiB)",
+                usize::MAX / MIB
+            ))
+        }
+    };
+    let nest = should_nest(input, read);
+    let read = CsvReadOpts {
+        flat: true,
+        ..read.clone()
+    };
+    let mut emit = |record: Value| f(if nest { unflatten(record)? } else { record });
+
+    let mut columns = ColumnCheck::default()
+        .paths(
+            "--sort-by",
+            sort.sort_by.iter().map(|key| key.column.clone()),
+        )
+        .paths("--dedup-by", sort.dedup_by.iter().cloned());
+    let mut seq = 0;
+    let mut read_into = |sorter: &mut Sorter| {
+        read_records(input, &read, force, |record| {
+            columns.check(&record)?;
+            seq += 1;
+            sorter.push(seq, record)
+        })
+    };
+
+    let mut dedup = Dedup::new(&sort.dedup_by);
+    let adjacent = sort
+        .sort_by
+        .get(..sort.dedup_by.len())
+        .is_some_and(|prefix| {
+            prefix
+                .iter()
+                .all(|key| key.mode == SortMode::Lexical && sort.dedup_by.contains(&key.column))
+        });
+    if sort.dedup_by.is_empty() || adjacent {
+        let mut sorter = Sorter::new(sort.sort_by.clone(), budget);
+        let header = read_into(&mut sorter)?;
+        sorter.finish(|_, record| {
+            if dedup.is_new(&record) {
+                emit(record)
+            } else {
+                Ok(())
+            }
+        })?;
+        return Ok(header);
+    }
+
+    // 按去重的列分组，组内按 `--sort-by` 和输入顺序排列，每组的第一行即排序后第一次出现的行。
+    // 分组的结果边输出边交给第二次排序，两次排序同时占用内存，各分一半上限
+    let group_by = sort.dedup_by.iter().map(|column| SortKey {
+        column: column.clone(),
+        mode: SortMode::Lexical,
+        descending: false,
+    });
+    let mut grouped = Sorter::new(
+        group_by.chain(sort.sort_by.iter().cloned()).collect(),
+        budget / 2,
+    );
+    let mut sorter = Sorter::new(sort.sort_by.clone(), budget - budget / 2);
+    let header = read_into(&mut grouped)?;
+    grouped.finish(|seq, record| {
+        if dedup.is_new(&record) {
+            sorter.push(seq, record)
+        } else {
+            Ok(())
+        }
+    })?;
+    sorter.finish(|_, record| emit(record))?;
+    Ok(header)
+}
+
+/// 记录排序时使用的值
+#[derive(Debug)]
+enum SortValue {
+    /// 空字符串、null 或缺少该列
+    Empty,
+    Number(f64),
+    Text(String),
+}
+
+/// 待排序的记录，`seq` 为输入中的序号，值相同时按序号排列，使排序是稳定的
+struct Entry {
+    values: Vec<SortValue>,
+    seq: u64,
+    record: Value,
+}
+
+/// 外部归并排序：记录先在内存中累积，估算的占用超过上限时排序后写入临时文件（一个 run），
+/// 最后用堆归并各 run。全部记录都在内存中时直接排序输出
+struct Sorter {
+    keys: Vec<SortKey>,
+    budget: usize,
+    buffer: Vec<Entry>,
+    size: usize,
+    runs: Vec<TempPath>,
+}
+
+impl Sorter {
+    fn new(keys: Vec<SortKey>, budget: usize) -> Self {
+        Self {
+            keys,
+            budget,
+            buffer: Vec::new(),
+            size: 0,
+            runs: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, seq: u64, record: Value) -> Result<()> {
+        let entry = Entry {
+            values: sort_values(&record, &self.keys),
+            seq,
+            record,
+        };
+        self.size += entry_size(&entry);
+        self.buffer.push(entry);
+        if self.size >= self.budget {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    /// 序号各不相同，不稳定排序的结果也是确定的，且不需要额外的缓冲区
+    fn sort_buffer(&mut self) {
+        let keys = &self.keys;
+        self.buffer
+            .sort_unstable_by(|a, b| compare_entries(a, b, keys));
+    }
+
+    fn spill(&mut self) -> Result<()> {
+        self.sort_buffer();
+        let mut run = RunWriter::new()?;
+        for entry in self.buffer.drain(..) {
+            run.write(entry.seq, &entry.record)?;
+        }
+        self.runs.push(run.finish()?);
+        self.buffer.shrink_to_fit();
+        self.size = 0;
+        Ok(())
+    }
+
+    /// 按顺序把记录及其序号交给 `f`
+    fn finish(mut self, mut f: impl FnMut(u64, Value) -> Result<()>) -> Result<()> {
+        if self.runs.is_empty() {
+            self.sort_buffer();
+            return self
+                .buffer
+                .into_iter()
+                .try_for_each(|entry| f(entry.seq, entry.record));
+        }
+        if !self.buffer.is_empty() {
+            self.spill()?;
+        }
+        let mut runs = std::mem::take(&mut self.runs);
+        while runs.len() > MERGE_FAN_IN {
+            let mut merged = Vec::new();
+            let mut rest = runs.into_iter();
+            loop {
+                let group: Vec<TempPath> = rest.by_ref().take(MERGE_FAN_IN).collect();
+                if group.len() <= 1 {
+                    merged.extend(group);
+                    break;
+                }
+                let mut run = RunWriter::new()?;
+                merge(&group, &self.keys, |seq, record| run.write(seq, &record))?;
+                merged.push(run.finish()?);
+            }
+            runs = merged;
+        }
+        merge(&runs, &self.keys, f)
+    }
+}
+
+/// 写入一个 run，每行为 `[序号, 记录]`
+struct RunWriter(BufWriter<NamedTempFile>);
+
+impl RunWriter {
+    fn new() -> Result<Self> {
+        Ok(Self(BufWriter::new(NamedTempFile::new()?)))
+    }
+
+    fn write(&mut self, seq: u64, record: &Value) -> Result<()> {
+        serde_json::to_writer(&mut self.0, &(seq, record))?;
+        self.0.write_all(b"\n")?;
+        Ok(())
+    }
+
+    /// 写完即关闭文件，归并时再打开，run 再多也不占用文件描述符；返回的路径释放时删除文件
+    fn finish(self) -> Result<TempPath> {
+        let file = self.0.into_inner().map_err(|e| e.into_error())?;
+        Ok(file.into_temp_path())
+    }
+}
+
+/// 各 run 当前的第一条记录，按排序的反序比较，使 [`BinaryHeap`] 先弹出最小的记录
+struct Head<'a> {
+    entry: Entry,
+    run: usize,
+    keys: &'a [SortKey],
+}
+
+impl Ord for Head<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        compare_entries(&other.entry, &self.entry, self.keys)
+    }
+}
+
+impl PartialOrd for Head<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Head<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Head<'_> {}
+
+/// 归并多个已排序的 run，按顺序把记录交给 `f`
+fn merge(
+    runs: &[TempPath],
+    keys: &[SortKey],
+    mut f: impl FnMut(u64, Value) -> Result<()>,
+) -> Result<()> {
+    let mut readers = runs
+        .iter()
+        .map(|path| Ok(BufReader::new(File::open(path)?).lines()))
+        .collect::<Result<Vec<_>>>()?;
+    let mut heap = BinaryHeap::with_capacity(readers.len());
+    for (run, reader) in readers.iter_mut().enumerate() {
+        if let Some(entry) = next_entry(reader, keys)? {
+            heap.push(Head { entry, run, keys });
+        }
+    }
+    while let Some(Head { entry, run, .. }) = heap.pop() {
+        if let Some(next) = next_entry(&mut readers[run], keys)? {
+            heap.push(Head {
+                entry: next,
+                run,
+                keys,
+            });
+        }
+        f(entry.seq, entry.record)?;
+    }
+    Ok(())
+}
+
+fn next_entry(run: &mut Lines<BufReader<File>>, keys: &[SortKey]) -> Result<Option<Entry>> {
+    match run.next() {
+        Some(line) => {
+            let (seq, record): (u64, Value) = serde_json::from_str(&line?)?;
+            Ok(Some(Entry {
+                values: sort_values(&record, keys),
+                seq,
+                record,
+            }))
+        }
+        None => Ok(None),
+    }
+}
+
+fn sort_values(record: &Value, keys: &[SortKey]) -> Vec<SortValue> {
+    keys.iter()
+        .map(|key| match lookup(record, &key.column) {
+            None | Some(Value::Null) => SortValue::Empty,
+            Some(Value::String(s)) if s.is_empty() => SortValue::Empty,
+            Some(Value::Number(n)) if key.mode == SortMode::Numeric => {
+                SortValue::Number(n.as_f64().unwrap_or_default())
+            }
+            Some(value) => {
+                let text = match value {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                if key.mode == SortMode::Numeric {
+                    match text.trim().parse::<f64>() {
+                        Ok(n) if !n.is_nan() => return SortValue::Number(n),
+                        _ => {}
+                    }
+                }
+                SortValue::Text(text)
+            }
+        })
+        .collect()
+}
+
+fn compare_entries(a: &Entry, b: &Entry, keys: &[SortKey]) -> Ordering {
+    compare_values(&a.values, &b.values, keys).then(a.seq.cmp(&b.seq))
+}
+
+/// 依次比较各列；空值以及 `:num` 列中不是数字的值不论升序降序都排在最后
+fn compare_values(a: &[SortValue], b: &[SortValue], keys: &[SortKey]) -> Ordering {
+    for ((a, b), key) in a.iter().zip(b).zip(keys) {
+        let ordering = match (a, b) {
+            (SortValue::Empty, SortValue::Empty) => Ordering::Equal,
+            (SortValue::Empty, _) => return Ordering::Greater,
+            (_, SortValue::Empty) => return Ordering::Less,
+            (SortValue::Number(a), SortValue::Number(b)) => a.total_cmp(b),
+            (SortValue::Number(_), SortValue::Text(_)) => return Ordering::Less,
+            (SortValue::Text(_), SortValue::Number(_)) => return Ordering::Greater,
+            (SortValue::Text(a), SortValue::Text(b)) => match key.mode {
+                SortMode::Natural => natural_cmp(a, b),
+                _ => a.cmp(b),
+            },
+        };
+        let ordering = if key.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+/// 自然排序：把文本切分为数字段和非数字段，数字段按数值比较，其余按文本比较
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a, b);
+    loop {
+        if a.is_empty() || b.is_empty() {
+            return a.len().cmp(&b.len());
+        }
+        let (x, rest_a) = split_chunk(a);
+        let (y, rest_b) = split_chunk(b);
+        let is_digits = |s: &str| s.starts_with(|c: char| c.is_ascii_digit());
+        let ordering = if is_digits(x) && is_digits(y) {
+            // 去掉前导零后位数多的更大，位数相同时逐位比较，不受整数范围限制
+            let (x, y) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+            x.len().cmp(&y.len()).then_with(|| x.cmp(y))
+        } else {
+            x.cmp(y)
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+        (a, b) = (rest_a, rest_b);
+    }
+}
+
+/// 取出开头连续的数字或连续的非数字
+fn split_chunk(s: &str) -> (&str, &str) {
+    let digits = s.starts_with(|c: char| c.is_ascii_digit());
+    let end = s
+        .find(|c: char| c.is_ascii_digit() != digits)
+        .unwrap_or(s.len());
+    s.split_at(end)
+}
+
+/// 一条待排序的记录在内存中占用的大致字节数，包括排序值中文本的拷贝
+fn entry_size(entry: &Entry) -> usize {
+    let values: usize = entry
+        .values
+        .iter()
+        .map(|value| match value {
+            SortValue::Text(text) => text.capacity() + ALLOC_OVERHEAD,
+            _ => 0,
+        })
+        .sum();
+    size_of::<Entry>()
+        + entry.values.capacity() * size_of::<SortValue>()
+        + ALLOC_OVERHEAD
+        + values
+        + heap_size(&entry.record)
+}
+
+/// 值在堆上占用的大致字节数，对象按每个字段的键、值和索引开销计算
+fn heap_size(value: &Value) -> usize {
+    match value {
+        Value::String(s) if s.capacity() > 0 => s.capacity() + ALLOC_OVERHEAD,
+        Value::Array(items) => {
+            items.capacity() * size_of::<Value>()
+                + ALLOC_OVERHEAD
+                + items.iter().map(heap_size).sum::<usize>()
+        }
+        Value::Object(map) => {
+            2 * ALLOC_OVERHEAD
+                + map
+                    .iter()
+                    .map(|(key, value)| {
+                        size_of::<(String, Value)>()
+                            + MAP_ENTRY_OVERHEAD
+                            + key.capacity()
+                            + ALLOC_OVERHEAD
+                            + heap_size(value)
+                    })
+                    .sum::<usize>()
+        }
+        _ => 0,
+    }
+}
+
+/// 按 `--dedup-by` 的列去重：记录按这些列排序后相同的键相邻，只记住前一行的键
+struct Dedup {
+    columns: Vec<String>,
+    last: Option<Vec<String>>,
+}
+
+impl Dedup {
+    fn new(columns: &[String]) -> Self {
+        Self {
+            columns: columns.to_vec(),
+            last: None,
+        }
+    }
+
+    /// 键与前一行不同时返回 `true`；没有指定 `--dedup-by` 时总是返回 `true`
+    fn is_new(&mut self, record: &Value) -> bool {
+        if self.columns.is_empty() {
+            return true;
+        }
+        let key: Vec<String> = self
+            .columns
+            .iter()
+            .map(|column| match lookup(record, column) {
+                None | Some(Value::Null) => String::new(),
+                Some(Value::String(s)) => s.clone(),
+                Some(value) => value.to_string(),
+            })
+            .collect();
+        if self.last.as_ref() == Some(&key) {
+            return false;
+        }
+        self.last = Some(key);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn natural_order() {
+        let mut names = vec![
+            "a10",
+            "a2",
+            "a02b",
+            "a",
+            "b1",
+            "a2b",
+            "a1000000000000000000000",
+        ];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            names,
+            [
+                "a",
+                "a2",
+                "a02b",
+                "a2b",
+                "a10",
+                "a1000000000000000000000",
+                "b1"
+            ]
+        );
+        assert_eq!(natural_cmp("x007", "x7"), Ordering::Equal);
+    }
+
+    #[test]
+    fn spilled_runs_merge_stably() {
+        // 上限为 1 字节时每条记录都单独写入一个 run，run 数超过一轮归并的数量
+        let keys = vec!["k:desc".parse::<SortKey>().unwrap()];
+        let mut sorter = Sorter::new(keys, 1);
+        let count = MERGE_FAN_IN as u64 * 3;
+        for seq in 0..count {
+            sorter
+                .push(seq, json!({"k": (seq % 5).to_string()}))
+                .unwrap();
+        }
+        assert_eq!(sorter.runs.len() as u64, count);
+        let mut order = Vec::new();
+        sorter
+            .finish(|seq, record| {
+                order.push((record["k"].as_str().unwrap().to_string(), seq));
+                Ok(())
+            })
+            .unwrap();
+        let mut expected = order.clone();
+        expected.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        assert_eq!(order.len() as u64, count);
+        assert_eq!(order, expected);
+    }
+}
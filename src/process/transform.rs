@@ -0,0 +1,103 @@
+use anyhow::{anyhow, Result};
+use serde_json::{Map, Value};
+
+use super::nested::ColumnCheck;
+use crate::opts::CsvReadOpts;
+
+/// 列的选择、排除与重命名，依次按 `--select`、`--exclude`、`--rename` 应用
+#[derive(Debug)]
+pub(crate) struct Projection {
+    select: Option<Vec<String>>,
+    exclude: Vec<String>,
+    rename: Vec<(String, String)>,
+    columns: ColumnCheck,
+}
+
+impl Projection {
+    pub fn new(opts: &CsvReadOpts) -> Self {
+        Self {
+            select: opts.select.clone(),
+            exclude: opts.exclude.clone(),
+            rename: opts.rename.clone(),
+            // 列投影只作用于顶层字段，列名不能是嵌套路径
+            columns: ColumnCheck::default()
+                .keys("--select", opts.select.iter().flatten().cloned())
+                .keys("--exclude", opts.exclude.iter().cloned())
+                .keys("--rename", opts.rename.iter().map(|(from, _)| from.clone())),
+        }
+    }
+
+    pub fn apply(&mut self, record: Value) -> Result<Value> {
+        if record.is_object() {
+            self.columns.check(&record)?;
+        }
+        let Value::Object(mut map) = record else {
+            return Ok(record);
+        };
+
+        if let Some(select) = &self.select {
+            let mut selected = Map::with_capacity(select.len());
+            for name in select {
+                if let Some(value) = map.remove(name) {
+                    selected.insert(name.clone(), value);
+                }
+            }
+            map = selected;
+        }
+        if !self.exclude.is_empty() {
+            map.retain(|name, _| !self.exclude.contains(name));
+        }
+        if !self.rename.is_empty() {
+            let mut renamed = Map::with_capacity(map.len());
+            for (name, value) in map {
+                let name = match self.rename.iter().find(|(from, _)| *from == name) {
+                    Some((_, to)) => to.clone(),
+                    None => name,
+                };
+                // 改名后与另一列同名时，后写入的值会覆盖前一列
+                if renamed.contains_key(&name) {
+                    return Err(anyhow!("Column `{}` already exists in --rename", name));
+                }
+                renamed.insert(name, value);
+            }
+            map = renamed;
+        }
+        Ok(Value::Object(map))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use serde_json::json;
+
+    fn project(args: &[&str]) -> Projection {
+        let opts = CsvReadOpts::parse_from(["csv"].iter().chain(args));
+        Projection::new(&opts)
+    }
+
+    #[test]
+    fn select_exclude_rename() {
+        let record = json!({"a": 1, "b": 2, "c": 3});
+        let mut projection = project(&["--select", "c,a", "--rename", "a=x"]);
+        assert_eq!(
+            projection.apply(record.clone()).unwrap(),
+            json!({"c": 3, "x": 1})
+        );
+        let mut projection = project(&["--exclude", "b", "--rename", "a=b,c=a"]);
+        assert_eq!(projection.apply(record).unwrap(), json!({"b": 1, "a": 3}));
+    }
+
+    #[test]
+    fn rejects_unknown_and_colliding_columns() {
+        let record = json!({"a": 1, "b": 2});
+        for args in [
+            ["--select", "z"],
+            ["--rename", "a=b"],
+            ["--rename", "a=x,b=x"],
+        ] {
+            assert!(project(&args).apply(record.clone()).is_err(), "{:?}", args);
+        }
+    }
+}
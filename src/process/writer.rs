@@ -0,0 +1,559 @@
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use std::{
+    collections::{HashMap, HashSet},
+    io::Write,
+};
+
+use super::binary::{BsonWriter, CborWriter, MsgpackWriter};
+use super::columnar::{Columnar, ColumnarWriter};
+use super::nested::flatten;
+use super::xml::XmlWriter;
+use crate::opts::{Compression, OutputFormat};
+
+/// 逐条写出记录，内存中最多只保留一条记录
+pub(crate) trait RecordWriter {
+    fn write_record(&mut self, record: &Value) -> Result<()>;
+
+    /// 输入的表头，在 [`finish`](Self::finish) 之前传入；表格类输出在没有记录时据此输出表头
+    fn set_header(&mut self, _columns: Vec<String>) {}
+
+    /// 写出结尾并刷新缓冲区
+    fn finish(self: Box<Self>) -> Result<()>;
+}
+
+/// 与具体输出格式相关的写入配置
+#[derive(Debug, Clone)]
+pub(crate) struct WriterConfig {
+    /// CSV 输出的分隔符
+    pub delimiter: u8,
+    /// CSV 输出是否写表头
+    pub header: bool,
+    /// CSV 输出的列顺序，未指定时取第一条记录的字段顺序
+    pub columns: Option<Vec<String>>,
+    /// TOML 输出的顶层键名
+    pub toml_root: String,
+    /// TOML 输出按该列的值作为键生成表，而不是表数组
+    pub key_by: Option<String>,
+    /// XML 输出的根元素名
+    pub xml_root: String,
+    /// XML 输出的行元素名
+    pub xml_row: String,
+    /// XML 输出把字段写为属性
+    pub xml_attributes: bool,
+    /// Parquet/Arrow 输出的压缩算法，未指定时使用各自的默认值
+    pub compression: Option<Compression>,
+    /// Parquet 的 row group 行数，也是 Arrow 的 record batch 行数
+    pub row_group_size: usize,
+}
+
+impl Default for WriterConfig {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            header: true,
+            columns: None,
+            toml_root: "records".to_string(),
+            key_by: None,
+            xml_root: "records".to_string(),
+            xml_row: "record".to_string(),
+            xml_attributes: false,
+            compression: None,
+            row_group_size: 1024 * 1024,
+        }
+    }
+}
+
+/// 根据输出格式创建对应的流式写入器
+pub(crate) fn new_writer<W: Write + 'static>(
+    format: OutputFormat,
+    out: W,
+    config: &WriterConfig,
+) -> Result<Box<dyn RecordWriter>> {
+    Ok(match format {
+        OutputFormat::Csv => Box::new(CsvWriter {
+            writer: csv::WriterBuilder::new()
+                .delimiter(config.delimiter)
+                .from_writer(out),
+            header: config.header,
+            strict: config.columns.is_none(),
+            columns: config.columns.clone(),
+            count: 0,
+        }),
+        OutputFormat::Json => Box::new(JsonArrayWriter { out, count: 0 }),
+        OutputFormat::Ndjson => Box::new(NdjsonWriter { out }),
+        OutputFormat::Yaml => Box::new(YamlWriter { out, count: 0 }),
+        OutputFormat::Markdown => Box::new(MarkupWriter::new(out, Markup::Markdown, config)),
+        OutputFormat::Html => Box::new(MarkupWriter::new(out, Markup::Html, config)),
+        OutputFormat::Asciidoc => Box::new(MarkupWriter::new(out, Markup::Asciidoc, config)),
+        OutputFormat::Xml => Box::new(XmlWriter::new(out, config)),
+        OutputFormat::Msgpack => Box::new(MsgpackWriter::new(out)?),
+        OutputFormat::Cbor => Box::new(CborWriter::new(out)),
+        OutputFormat::Bson => Box::new(BsonWriter::new(out)),
+        OutputFormat::Parquet => Box::new(ColumnarWriter::new(out, Columnar::Parquet, config)?),
+        OutputFormat::Arrow => Box::new(ColumnarWriter::new(out, Columnar::Arrow, config)?),
+        OutputFormat::Toml => Box::new(TomlWriter {
+            out,
+            root: config.toml_root.clone(),
+            key_by: config.key_by.clone(),
+            seen: HashSet::new(),
+            count: 0,
+        }),
+    })
+}
+
+/// 输出与 `serde_json::to_string_pretty` 一致的 JSON 数组，与其他格式一样以换行结尾
+struct JsonArrayWriter<W> {
+    out: W,
+    count: usize,
+}
+
+impl<W: Write> RecordWriter for JsonArrayWriter<W> {
+    fn write_record(&mut self, record: &Value) -> Result<()> {
+        self.out
+            .write_all(if self.count == 0 { b"[\n" } else { b",\n" })?;
+        // JSON 字符串中的换行会被转义，因此可以安全地逐行缩进
+        let pretty = serde_json::to_string_pretty(record)?;
+        for (i, line) in pretty.lines().enumerate() {
+            if i > 0 {
+                self.out.write_all(b"\n")?;
+            }
+            write!(self.out, "  {}", line)?;
+        }
+        self.count += 1;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        self.out
+            .write_all(if self.count == 0 { b"[]\n" } else { b"\n]\n" })?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// 每行一个 JSON 对象
+struct NdjsonWriter<W> {
+    out: W,
+}
+
+impl<W: Write> RecordWriter for NdjsonWriter<W> {
+    fn write_record(&mut self, record: &Value) -> Result<()> {
+        serde_json::to_writer(&mut self.out, record)?;
+        self.out.write_all(b"\n")?;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// 每条记录序列化为单元素序列，拼接后即为完整的 YAML 序列
+struct YamlWriter<W> {
+    out: W,
+    count: usize,
+}
+
+impl<W: Write> RecordWriter for YamlWriter<W> {
+    fn write_record(&mut self, record: &Value) -> Result<()> {
+        self.out
+            .write_all(serde_yaml::to_string(&[record])?.as_bytes())?;
+        self.count += 1;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        if self.count == 0 {
+            self.out.write_all(b"[]\n")?;
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// 每条记录输出为一个 `[[root]]` 表；指定 `key_by` 时输出为 `[root.<key>]` 表
+struct TomlWriter<W> {
+    out: W,
+    root: String,
+    key_by: Option<String>,
+    /// 已出现过的键，用于检查 `key_by` 列的唯一性
+    seen: HashSet<String>,
+    count: usize,
+}
+
+impl<W: Write> RecordWriter for TomlWriter<W> {
+    fn write_record(&mut self, record: &Value) -> Result<()> {
+        let mut record = record.clone();
+        strip_nulls(&mut record);
+
+        // TOML 的顶层必须是表，因此把记录放到 `root` 键下
+        let doc = match &self.key_by {
+            Some(column) => {
+                let key = match record
+                    .as_object_mut()
+                    .and_then(|map| map.shift_remove(column))
+                {
+                    Some(Value::String(s)) => s,
+                    Some(Value::Null) | None => {
+                        return Err(anyhow!(
+                            "Record {} has no value for --key-by column `{}`",
+                            self.count + 1,
+                            column
+                        ))
+                    }
+                    Some(other) => other.to_string(),
+                };
+                if !self.seen.insert(key.clone()) {
+                    return Err(anyhow!(
+                        "Duplicate key `{}` in --key-by column `{}`",
+                        key,
+                        column
+                    ));
+                }
+                json!({ &self.root: { key: record } })
+            }
+            None => json!({ &self.root: [record] }),
+        };
+
+        if self.count > 0 {
+            self.out.write_all(b"\n")?;
+        }
+        self.out
+            .write_all(toml::to_string_pretty(&doc)?.as_bytes())?;
+        self.count += 1;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        if self.count == 0 {
+            let empty = match self.key_by {
+                Some(_) => json!({ &self.root: {} }),
+                None => json!({ &self.root: [] }),
+            };
+            self.out
+                .write_all(toml::to_string_pretty(&empty)?.as_bytes())?;
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// 嵌套对象按 `a.b`、数组按 `a[0]` 展开为扁平的列
+struct CsvWriter<W: Write> {
+    writer: csv::Writer<W>,
+    header: bool,
+    /// 列由第一条记录决定时，后续记录出现新字段视为错误；显式指定列时只输出这些列
+    strict: bool,
+    columns: Option<Vec<String>>,
+    count: usize,
+}
+
+impl<W: Write> RecordWriter for CsvWriter<W> {
+    fn write_record(&mut self, record: &Value) -> Result<()> {
+        let row = flatten_row(record, &mut self.columns, self.strict, self.count)?;
+        if let (0, true, Some(columns)) = (self.count, self.header, &self.columns) {
+            self.writer.write_record(columns)?;
+        }
+        self.writer.write_record(&row)?;
+        self.count += 1;
+        Ok(())
+    }
+
+    fn set_header(&mut self, columns: Vec<String>) {
+        self.columns.get_or_insert(columns);
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        if let (0, true, Some(columns)) = (self.count, self.header, &self.columns) {
+            self.writer.write_record(columns)?;
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// 表格标记语言
+#[derive(Debug, Clone, Copy)]
+enum Markup {
+    Markdown,
+    Html,
+    Asciidoc,
+}
+
+/// 输出 Markdown、HTML 或 AsciiDoc 表格，列的处理方式与 CSV 相同
+struct MarkupWriter<W> {
+    out: W,
+    markup: Markup,
+    strict: bool,
+    columns: Option<Vec<String>>,
+    /// 表头已经写出
+    started: bool,
+    count: usize,
+}
+
+impl<W: Write> MarkupWriter<W> {
+    fn new(out: W, markup: Markup, config: &WriterConfig) -> Self {
+        Self {
+            out,
+            markup,
+            strict: config.columns.is_none(),
+            columns: config.columns.clone(),
+            started: false,
+            count: 0,
+        }
+    }
+
+    fn write_header(&mut self, columns: &[String]) -> Result<()> {
+        let cells: Vec<String> = columns.iter().map(|c| self.markup.escape(c)).collect();
+        match self.markup {
+            Markup::Markdown => {
+                writeln!(self.out, "| {} |", cells.join(" | "))?;
+                writeln!(self.out, "|{}", " --- |".repeat(cells.len()))?;
+            }
+            Markup::Html => {
+                writeln!(self.out, "<table>\n  <thead>")?;
+                writeln!(
+                    self.out,
+                    "    <tr><th>{}</th></tr>",
+                    cells.join("</th><th>")
+                )?;
+                writeln!(self.out, "  </thead>\n  <tbody>")?;
+            }
+            Markup::Asciidoc => {
+                writeln!(self.out, "[options=\"header\"]\n|===")?;
+                writeln!(self.out, "| {}\n", cells.join(" | "))?;
+            }
+        }
+        self.started = true;
+        Ok(())
+    }
+
+    fn write_row(&mut self, row: &[String]) -> Result<()> {
+        let cells: Vec<String> = row.iter().map(|c| self.markup.escape(c)).collect();
+        match self.markup {
+            Markup::Markdown => writeln!(self.out, "| {} |", cells.join(" | "))?,
+            Markup::Html => writeln!(
+                self.out,
+                "    <tr><td>{}</td></tr>",
+                cells.join("</td><td>")
+            )?,
+            Markup::Asciidoc => writeln!(self.out, "| {}", cells.join(" | "))?,
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> RecordWriter for MarkupWriter<W> {
+    fn write_record(&mut self, record: &Value) -> Result<()> {
+        let row = flatten_row(record, &mut self.columns, self.strict, self.count)?;
+        if !self.started {
+            let columns = self.columns.clone().unwrap_or_default();
+            self.write_header(&columns)?;
+        }
+        self.write_row(&row)?;
+        self.count += 1;
+        Ok(())
+    }
+
+    fn set_header(&mut self, columns: Vec<String>) {
+        self.columns.get_or_insert(columns);
+    }
+
+    /// 没有记录时只输出表头；列也无法确定时不输出任何内容
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        if !self.started {
+            if let Some(columns) = self.columns.clone() {
+                self.write_header(&columns)?;
+            }
+        }
+        if self.started {
+            match self.markup {
+                Markup::Markdown => {}
+                Markup::Html => writeln!(self.out, "  </tbody>\n</table>")?,
+                Markup::Asciidoc => writeln!(self.out, "|===")?,
+            }
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+impl Markup {
+    /// 转义单元格内容，单元格中的换行转为各语言的换行写法
+    fn escape(self, text: &str) -> String {
+        let mut ret = String::with_capacity(text.len());
+        for c in text.chars() {
+            match (self, c) {
+                (Markup::Markdown, '\\' | '|' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '&') => {
+                    ret.push('\\');
+                    ret.push(c);
+                }
+                (Markup::Markdown, '\n') => ret.push_str("<br>"),
+                (Markup::Html, '&') => ret.push_str("&amp;"),
+                (Markup::Html, '<') => ret.push_str("&lt;"),
+                (Markup::Html, '>') => ret.push_str("&gt;"),
+                (Markup::Html, '"') => ret.push_str("&quot;"),
+                (Markup::Html, '\'') => ret.push_str("&#39;"),
+                (Markup::Html, '\n') => ret.push_str("<br>"),
+                // `{name}` 会被当作属性引用
+                (Markup::Asciidoc, '|' | '{') => {
+                    ret.push('\\');
+                    ret.push(c);
+                }
+                (Markup::Asciidoc, '\n') => ret.push_str(" +\n"),
+                (_, '\r') => {}
+                _ => ret.push(c),
+            }
+        }
+        ret
+    }
+}
+
+/// 把记录按 `columns` 展开为一行单元格，`columns` 未指定时取第一条记录的字段
+///
+/// `strict` 时后续记录出现新字段视为错误，否则只输出指定的列
+fn flatten_row(
+    record: &Value,
+    columns: &mut Option<Vec<String>>,
+    strict: bool,
+    index: usize,
+) -> Result<Vec<String>> {
+    let mut fields = Vec::new();
+    flatten(record, String::new(), &mut fields);
+
+    let columns = columns.get_or_insert_with(|| fields.iter().map(|(k, _)| k.clone()).collect());
+    let mut fields = fields.into_iter().collect::<HashMap<_, _>>();
+    let row = columns
+        .iter()
+        .map(|column| fields.remove(column).unwrap_or_default())
+        .collect::<Vec<_>>();
+    if let Some(extra) = fields.keys().next().filter(|_| strict) {
+        return Err(anyhow!(
+            "Record {} has field `{}` which is not in the output columns, use --columns to list all columns",
+            index + 1,
+            extra
+        ));
+    }
+    Ok(row)
+}
+
+/// TOML 没有 null，输出前去掉值为 null 的字段
+fn strip_nulls(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.retain(|_, v| !v.is_null());
+            map.values_mut().for_each(strip_nulls);
+        }
+        Value::Array(items) => {
+            items.retain(|v| !v.is_null());
+            items.iter_mut().for_each(strip_nulls);
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{cell::RefCell, rc::Rc};
+
+    /// 写入器拿走输出的所有权，通过共享的缓冲区读回写出的内容
+    #[derive(Clone, Default)]
+    struct Buffer(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn render(format: OutputFormat, records: &[Value], header: Option<&[&str]>) -> String {
+        let buffer = Buffer::default();
+        let mut writer = new_writer(format, buffer.clone(), &WriterConfig::default()).unwrap();
+        for record in records {
+            writer.write_record(record).unwrap();
+        }
+        if let Some(header) = header {
+            writer.set_header(header.iter().map(|s| s.to_string()).collect());
+        }
+        writer.finish().unwrap();
+        let bytes = buffer.0.borrow().clone();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn csv_header_without_records() {
+        assert_eq!(render(OutputFormat::Csv, &[], Some(&["a", "b"])), "a,b\n");
+        assert_eq!(render(OutputFormat::Csv, &[], None), "");
+        // 有记录时按第一条记录的字段输出
+        let records = [json!({"b": 1, "a": {"c": 2}})];
+        assert_eq!(
+            render(OutputFormat::Csv, &records, Some(&["a.c", "b"])),
+            "b,a.c\n1,2\n"
+        );
+    }
+
+    #[test]
+    fn escapes_markup_cells() {
+        let text = "a|b *c* <d> & 'e' \"f\" {g}\r\nh";
+        assert_eq!(
+            Markup::Markdown.escape(text),
+            "a\\|b \\*c\\* \\<d\\> \\& 'e' \"f\" {g}<br>h"
+        );
+        assert_eq!(
+            Markup::Html.escape(text),
+            "a|b *c* &lt;d&gt; &amp; &#39;e&#39; &quot;f&quot; {g}<br>h"
+        );
+        assert_eq!(
+            Markup::Asciidoc.escape(text),
+            "a\\|b *c* <d> & 'e' \"f\" \\{g} +\nh"
+        );
+        assert_eq!(
+            Markup::Markdown.escape("[x](y)_`z`\\"),
+            "\\[x\\](y)\\_\\`z\\`\\\\"
+        );
+    }
+
+    #[test]
+    fn renders_markup_tables() {
+        let records = [
+            json!({"name": "a|b", "n": {"x": 1}}),
+            json!({"name": "<c>"}),
+        ];
+        assert_eq!(
+            render(OutputFormat::Markdown, &records, None),
+            "| name | n.x |\n| --- | --- |\n| a\\|b | 1 |\n| \\<c\\> |  |\n"
+        );
+        assert_eq!(
+            render(OutputFormat::Html, &records, None),
+            "<table>\n  <thead>\n    <tr><th>name</th><th>n.x</th></tr>\n  </thead>\n  <tbody>\n\
+             \x20   <tr><td>a|b</td><td>1</td></tr>\n    <tr><td>&lt;c&gt;</td><td></td></tr>\n\
+             \x20 </tbody>\n</table>\n"
+        );
+        assert_eq!(
+            render(OutputFormat::Asciidoc, &records, None),
+            "[options=\"header\"]\n|===\n| name | n.x\n\n| a\\|b | 1\n| <c> | \n|===\n"
+        );
+        // 没有记录时只输出表头
+        assert_eq!(
+            render(OutputFormat::Markdown, &[], Some(&["a"])),
+            "| a |\n| --- |\n"
+        );
+    }
+
+    #[test]
+    fn markup_rejects_new_fields() {
+        let buffer = Buffer::default();
+        let mut writer =
+            new_writer(OutputFormat::Markdown, buffer, &WriterConfig::default()).unwrap();
+        writer.write_record(&json!({"a": 1})).unwrap();
+        let err = writer.write_record(&json!({"a": 2, "b": 3})).unwrap_err();
+        assert!(err.to_string().contains("Record 2 has field `b`"));
+    }
+}
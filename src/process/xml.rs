@@ -0,0 +1,369 @@
+use anyhow::{anyhow, Result};
+use quick_xml::{
+    encoding::Decoder,
+    escape::resolve_predefined_entity,
+    events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event},
+    Reader, Writer,
+};
+use serde_json::{Map, Value};
+use std::io::Write;
+
+use super::nested::flatten;
+use super::writer::{RecordWriter, WriterConfig};
+use crate::utils::get_reader;
+
+/// 元素同时有属性或子元素和文本时，文本保存在该字段中
+const TEXT_FIELD: &str = "#text";
+
+/// 每条记录输出为根元素下的一个行元素，字段为子元素（嵌套对象为嵌套元素，数组为重复的元素），
+/// 属性模式下字段按 `a.b`、`a[0]` 展开为行元素的属性
+pub(crate) struct XmlWriter<W: Write> {
+    writer: Writer<W>,
+    root: String,
+    row: String,
+    attributes: bool,
+    started: bool,
+}
+
+impl<W: Write> XmlWriter<W> {
+    pub fn new(out: W, config: &WriterConfig) -> Self {
+        Self {
+            writer: Writer::new_with_indent(out, b' ', 2),
+            root: element_name(&config.xml_root),
+            row: element_name(&config.xml_row),
+            attributes: config.xml_attributes,
+            started: false,
+        }
+    }
+
+    fn start(&mut self) -> Result<()> {
+        if !self.started {
+            self.writer
+                .write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+            self.writer
+                .write_event(Event::Start(BytesStart::new(self.root.as_str())))?;
+            self.started = true;
+        }
+        Ok(())
+    }
+
+    fn write_value(&mut self, name: &str, value: &Value) -> Result<()> {
+        match value {
+            Value::Object(map) if map.is_empty() => {
+                self.writer
+                    .write_event(Event::Empty(BytesStart::new(name)))?;
+            }
+            Value::Object(map) => {
+                self.writer
+                    .write_event(Event::Start(BytesStart::new(name)))?;
+                for (key, value) in map {
+                    self.write_value(&element_name(key), value)?;
+                }
+                self.writer.write_event(Event::End(BytesEnd::new(name)))?;
+            }
+            Value::Array(items) => {
+                for item in items {
+                    self.write_value(name, item)?;
+                }
+            }
+            Value::Null => self
+                .writer
+                .write_event(Event::Empty(BytesStart::new(name)))?,
+            Value::String(s) => {
+                self.writer
+                    .create_element(name)
+                    .write_text_content(BytesText::new(s))?;
+            }
+            Value::Bool(_) | Value::Number(_) => {
+                self.writer
+                    .create_element(name)
+                    .write_text_content(BytesText::new(&value.to_string()))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> RecordWriter for XmlWriter<W> {
+    fn write_record(&mut self, record: &Value) -> Result<()> {
+        self.start()?;
+        if self.attributes {
+            let mut fields = Vec::new();
+            flatten(record, String::new(), &mut fields);
+            let mut element = BytesStart::new(self.row.as_str());
+            for (key, value) in &fields {
+                element.push_attribute((element_name(key).as_str(), value.as_str()));
+            }
+            self.writer.write_event(Event::Empty(element))?;
+        } else {
+            let row = self.row.clone();
+            self.write_value(&row, record)?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        self.start()?;
+        self.writer
+            .write_event(Event::End(BytesEnd::new(self.root.as_str())))?;
+        let out = self.writer.get_mut();
+        out.write_all(b"\n")?;
+        out.flush()?;
+        Ok(())
+    }
+}
+
+/// 列名中不能出现在 XML 名称中的字符替换为 `_`，如 `Kit Number` 输出为 `<Kit_Number>`
+fn element_name(name: &str) -> String {
+    let mut ret: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '_' | '-' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if !ret.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+        ret.insert(0, '_');
+    }
+    ret
+}
+
+/// 正在读取的元素
+struct Element {
+    name: String,
+    fields: Map<String, Value>,
+    text: String,
+}
+
+impl Element {
+    fn new(start: &BytesStart, decoder: Decoder) -> Result<Self> {
+        let mut fields = Map::new();
+        for attr in start.attributes() {
+            let attr = attr?;
+            fields.insert(
+                String::from_utf8_lossy(attr.key.as_ref()).into_owned(),
+                Value::String(attr.decode_and_unescape_value(decoder)?.into_owned()),
+            );
+        }
+        Ok(Self {
+            name: String::from_utf8_lossy(start.name().as_ref()).into_owned(),
+            fields,
+            text: String::new(),
+        })
+    }
+
+    /// 重复出现的子元素合并为数组
+    fn add_child(&mut self, name: String, value: Value) {
+        match self.fields.get_mut(&name) {
+            Some(Value::Array(items)) => items.push(value),
+            Some(existing) => {
+                let first = existing.take();
+                *existing = Value::Array(vec![first, value]);
+            }
+            None => {
+                self.fields.insert(name, value);
+            }
+        }
+    }
+
+    /// 只有文本的元素为字符串，否则为对象；子元素之间的缩进空白被忽略
+    fn into_value(mut self) -> Value {
+        if self.fields.is_empty() {
+            return Value::String(self.text);
+        }
+        let text = self.text.trim();
+        if !text.is_empty() {
+            self.fields
+                .insert(TEXT_FIELD.to_string(), Value::String(text.to_string()));
+        }
+        Value::Object(self.fields)
+    }
+}
+
+/// 流式读取 XML，每个行元素转为一条记录，内存中最多只保留一条记录
+///
+/// `row` 为行元素名，可以出现在任意层级；未指定时根元素的每个子元素为一行
+pub(crate) fn read_xml(
+    input: &str,
+    row: Option<&str>,
+    mut f: impl FnMut(Value) -> Result<()>,
+) -> Result<()> {
+    let mut reader = Reader::from_reader(get_reader(input)?);
+    let mut buf = Vec::new();
+    // 当前所在的层级，根元素为 1
+    let mut depth = 0;
+    // 正在读取的行元素及其子元素，为空时表示不在行内
+    let mut stack: Vec<Element> = Vec::new();
+    let is_row = |start: &BytesStart, depth: usize| match row {
+        Some(row) => start.name().as_ref() == row.as_bytes(),
+        None => depth == 2,
+    };
+
+    loop {
+        let event = reader
+            .read_event_into(&mut buf)
+            .map_err(|e| anyhow!("XML error at byte {}: {}", reader.error_position(), e))?;
+        match event {
+            Event::Start(start) => {
+                depth += 1;
+                if !stack.is_empty() || is_row(&start, depth) {
+                    stack.push(Element::new(&start, reader.decoder())?);
+                }
+            }
+            Event::Empty(start) if !stack.is_empty() || is_row(&start, depth + 1) => {
+                close(Element::new(&start, reader.decoder())?, &mut stack, &mut f)?;
+            }
+            Event::End(_) => {
+                depth -= 1;
+                if let Some(element) = stack.pop() {
+                    close(element, &mut stack, &mut f)?;
+                }
+            }
+            Event::Text(text) => {
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&text.xml_content()?);
+                }
+            }
+            Event::CData(data) => {
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&data.decode()?);
+                }
+            }
+            Event::GeneralRef(entity) => {
+                if let Some(element) = stack.last_mut() {
+                    match entity.resolve_char_ref()? {
+                        Some(c) => element.text.push(c),
+                        None => {
+                            let name = entity.decode()?;
+                            let value = resolve_predefined_entity(&name)
+                                .ok_or_else(|| anyhow!("Unknown XML entity `&{};`", name))?;
+                            element.text.push_str(value);
+                        }
+                    }
+                }
+            }
+            Event::Eof if depth > 0 => {
+                return Err(anyhow!(
+                    "Unexpected end of XML, {} element(s) not closed",
+                    depth
+                ))
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(())
+}
+
+/// 元素读取完毕：行内的元素加入父元素，行元素本身作为一条记录交给 `f`
+fn close(
+    element: Element,
+    stack: &mut [Element],
+    f: &mut impl FnMut(Value) -> Result<()>,
+) -> Result<()> {
+    let name = element.name.clone();
+    let value = element.into_value();
+    match stack.last_mut() {
+        Some(parent) => parent.add_child(name, value),
+        None if value.is_object() => f(value)?,
+        None => return Err(anyhow!("Row element <{}> has no fields", name)),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opts::OutputFormat;
+    use crate::process::writer::new_writer;
+    use serde_json::json;
+    use std::fs;
+    use tempfile::{NamedTempFile, TempDir};
+
+    fn read(xml: &str, row: Option<&str>) -> Result<Vec<Value>> {
+        let file = NamedTempFile::new()?;
+        fs::write(file.path(), xml)?;
+        let mut records = Vec::new();
+        read_xml(&file.path().to_string_lossy(), row, |record| {
+            records.push(record);
+            Ok(())
+        })?;
+        Ok(records)
+    }
+
+    #[test]
+    fn reads_rows() {
+        let xml = r#"<?xml version="1.0"?>
+<players>
+  <player id="1">
+    <name>Del Piero &amp; co</name>
+    <tag>a</tag>
+    <tag>b</tag>
+    <club city="Turin">Juventus</club>
+  </player>
+  <player id="2"><name><![CDATA[<Buffon>]]></name><note/></player>
+</players>"#;
+        assert_eq!(
+            read(xml, None).unwrap(),
+            [
+                json!({
+                    "id": "1",
+                    "name": "Del Piero & co",
+                    "tag": ["a", "b"],
+                    "club": {"city": "Turin", "#text": "Juventus"}
+                }),
+                json!({"id": "2", "name": "<Buffon>", "note": ""}),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_named_rows_at_any_depth() {
+        let xml =
+            "<doc><meta><v>1</v></meta><list><item><v>2</v></item><item v=\"3\"/></list></doc>";
+        assert_eq!(
+            read(xml, Some("item")).unwrap(),
+            [json!({"v": "2"}), json!({"v": "3"})]
+        );
+    }
+
+    #[test]
+    fn reports_errors() {
+        assert!(read("<a><b><c>1</c></b>", None).is_err());
+        assert!(read("<a><b>text only</b></a>", None).is_err());
+        assert!(read("<a><b><c>&nope;</c></b></a>", None).is_err());
+    }
+
+    #[test]
+    fn round_trips_written_records() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("out.xml");
+        let records = [
+            json!({"Kit Number": "10", "name": "A <&> \"B\"", "tags": ["x", "y"]}),
+            json!({"Kit Number": "1", "name": "C", "tags": ["z"]}),
+        ];
+        let mut writer = new_writer(
+            OutputFormat::Xml,
+            fs::File::create(&path).unwrap(),
+            &WriterConfig::default(),
+        )
+        .unwrap();
+        for record in &records {
+            writer.write_record(record).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let xml = fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            read(&xml, None).unwrap(),
+            [
+                json!({"Kit_Number": "10", "name": "A <&> \"B\"", "tags": ["x", "y"]}),
+                json!({"Kit_Number": "1", "name": "C", "tags": "z"}),
+            ]
+        );
+    }
+}
@@ -0,0 +1,208 @@
+use anyhow::{anyhow, Context, Result};
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
+use tempfile::{NamedTempFile, TempPath};
+
+/// 打开输入，`-` 表示标准输入
+pub fn get_reader(input: &str) -> Result<Box<dyn BufRead>> {
+    let reader: Box<dyn BufRead> = if input == "-" {
+        Box::new(io::stdin().lock())
+    } else {
+        Box::new(BufReader::new(File::open(input)?))
+    };
+    Ok(reader)
+}
+
+/// 把标准输入转存到临时文件，用于需要读多遍或先读一部分的场合；文件在返回值释放时删除
+pub fn spool_stdin() -> Result<NamedTempFile> {
+    let mut file = NamedTempFile::new()?;
+    io::copy(&mut io::stdin().lock(), &mut file)?;
+    file.flush()?;
+    Ok(file)
+}
+
+/// 打开输出，`-` 表示标准输出
+pub fn get_writer(output: &str) -> Result<Box<dyn Write>> {
+    let writer: Box<dyn Write> = if output == "-" {
+        Box::new(Stdout(BufWriter::new(io::stdout().lock())))
+    } else {
+        Box::new(BufWriter::new(File::create(output)?))
+    };
+    Ok(writer)
+}
+
+/// 写标准输出时发现管道已被下游关闭
+static STDOUT_CLOSED: AtomicBool = AtomicBool::new(false);
+
+/// 标准输出的管道是否已被下游关闭（如 `rcli ... | head`），此时写入失败不算错误
+pub fn stdout_closed() -> bool {
+    STDOUT_CLOSED.load(Ordering::Relaxed)
+}
+
+/// 标准输出；各格式的编码器把 IO 错误包装在各自的错误类型中，因此在这里记录管道关闭
+struct Stdout<W>(W);
+
+impl<W: Write> Stdout<W> {
+    fn check<T>(result: io::Result<T>) -> io::Result<T> {
+        if let Err(e) = &result {
+            if e.kind() == io::ErrorKind::BrokenPipe {
+                STDOUT_CLOSED.store(true, Ordering::Relaxed);
+            }
+        }
+        result
+    }
+}
+
+impl<W: Write> Write for Stdout<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Self::check(self.0.write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Self::check(self.0.flush())
+    }
+}
+
+/// 未指定输出文件时的默认输出：与输入文件同名，扩展名为输出格式；标准输入时输出到标准输出
+pub fn default_output(input: &str, extension: &str) -> String {
+    if input == "-" {
+        return input.to_string();
+    }
+    Path::new(input)
+        .with_extension(extension)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// 写入文件的输出：内容先写到目标目录下的临时文件，`commit` 时再改名为目标文件，
+/// 写到一半失败或进程中断时不会留下不完整的文件
+pub struct OutputFile {
+    /// 临时文件和目标文件，标准输出时为 `None`
+    pending: Option<(TempPath, PathBuf)>,
+    force: bool,
+}
+
+/// 打开输出，`-` 表示标准输出；输出文件已存在且没有 `force` 时报错，而不是覆盖
+pub fn create_output(output: &str, force: bool) -> Result<(Box<dyn Write>, OutputFile)> {
+    open_output(output, force, 0o666)
+}
+
+/// 与 [`create_output`] 相同，但文件只有所有者可读写，用于密码等敏感内容
+pub fn create_private_output(output: &str, force: bool) -> Result<(Box<dyn Write>, OutputFile)> {
+    open_output(output, force, 0o600)
+}
+
+#[cfg_attr(not(unix), allow(unused_variables))]
+fn open_output(output: &str, force: bool, mode: u32) -> Result<(Box<dyn Write>, OutputFile)> {
+    if output == "-" {
+        let pending = OutputFile {
+            pending: None,
+            force,
+        };
+        return Ok((get_writer(output)?, pending));
+    }
+
+    let path = PathBuf::from(output);
+    if !force && path.exists() {
+        return Err(anyhow!(
+            "Output file `{}` already exists, use --force to overwrite it",
+            output
+        ));
+    }
+    // 临时文件与目标文件在同一目录下，改名才是原子的
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let mut builder = tempfile::Builder::new();
+    builder.prefix(".rcli-").suffix(".tmp");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        // 临时文件默认只有所有者可读写，改为与直接创建文件时相同的权限（受 umask 限制）
+        builder.permissions(std::fs::Permissions::from_mode(mode));
+    }
+    let (file, temp) = builder
+        .tempfile_in(dir)
+        .with_context(|| format!("Cannot create output file `{}`", output))?
+        .into_parts();
+    let pending = OutputFile {
+        pending: Some((temp, path)),
+        force,
+    };
+    Ok((Box::new(BufWriter::new(file)), pending))
+}
+
+impl OutputFile {
+    /// 确保内容已写入磁盘后把临时文件改名为目标文件；未调用时临时文件在 drop 时被删除
+    pub fn commit(self) -> Result<()> {
+        let Some((temp, path)) = self.pending else {
+            return Ok(());
+        };
+        File::open(&temp)?.sync_all()?;
+        let persisted = if self.force {
+            temp.persist(&path)
+        } else {
+            // 写入期间目标文件被其他进程创建时同样不覆盖
+            temp.persist_noclobber(&path)
+        };
+        persisted.map_err(|e| anyhow!("Cannot write output file `{}`: {}", path.display(), e.error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn write(path: &str, content: &str, force: bool, commit: bool) -> Result<()> {
+        let (mut out, file) = create_output(path, force)?;
+        out.write_all(content.as_bytes())?;
+        out.flush()?;
+        drop(out);
+        if commit {
+            file.commit()?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn output_is_atomic_and_not_overwritten() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("out.csv");
+        let output = path.to_str().unwrap();
+
+        // 没有 commit 时不留下任何文件，包括临时文件
+        write(output, "partial", false, false).unwrap();
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+
+        write(output, "first", false, true).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first");
+        assert!(write(output, "second", false, true).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first");
+        write(output, "second", true, true).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn private_output_is_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("secret.txt");
+        let (mut out, file) = create_private_output(path.to_str().unwrap(), false).unwrap();
+        out.write_all(b"secret").unwrap();
+        out.flush().unwrap();
+        drop(out);
+        file.commit().unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}